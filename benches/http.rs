@@ -1,8 +1,6 @@
 #[macro_use]
 extern crate bencher;
 
-#[macro_use]
-extern crate nom;
 extern crate nomfun;
 extern crate jemallocator;
 
@@ -13,7 +11,9 @@ use bencher::{black_box, Bencher};
 
 //use nom::IResult;
 use nomfun::*;
+use nomfun::complete::{char, tag, take_while1};
 
+#[allow(dead_code)]
 #[derive(Debug)]
 struct Request<'a> {
     method:  &'a [u8],
@@ -21,37 +21,16 @@ struct Request<'a> {
     version: &'a [u8],
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 struct Header<'a> {
     name:  &'a [u8],
     value: Vec<&'a [u8]>,
 }
 
-fn is_token(c: u8) -> bool {
-    match c {
-        128...255 => false,
-        0...31    => false,
-        b'('      => false,
-        b')'      => false,
-        b'<'      => false,
-        b'>'      => false,
-        b'@'      => false,
-        b','      => false,
-        b';'      => false,
-        b':'      => false,
-        b'\\'     => false,
-        b'"'      => false,
-        b'/'      => false,
-        b'['      => false,
-        b']'      => false,
-        b'?'      => false,
-        b'='      => false,
-        b'{'      => false,
-        b'}'      => false,
-        b' '      => false,
-        _         => true,
-    }
-}
+// visible ASCII characters and DEL, without the separators
+const TOKEN: ByteSet = ByteSet::from_range(33, 127)
+  .difference(ByteSet::from_bytes(b"()<>@,;:\\\"/[]?={}"));
 
 fn not_line_ending(c: u8) -> bool {
     c != b'\r' && c != b'\n'
@@ -65,13 +44,13 @@ fn is_not_space(c: u8)        -> bool { c != b' ' }
 fn is_horizontal_space(c: u8) -> bool { c == b' ' || c == b'\t' }
 
 fn is_version(c: u8) -> bool {
-    c >= b'0' && c <= b'9' || c == b'.'
+    c.is_ascii_digit() || c == b'.'
 }
 
 //named!(line_ending, alt!(tag!("\r\n") | tag!("\n")));
 
-fn line_ending<'a>(i: &'a [u8]) -> IResult<&'a[u8], &'a[u8], (&'a[u8], u32)> {
-  tag::<(&[u8], u32)>(&b"\r\n"[..])(i).or(tag(&b"\n"[..])(i))
+fn line_ending(i: &[u8]) -> IResult<&[u8], &[u8]> {
+  tag(&b"\r\n"[..]).or(tag(&b"\n"[..])).parse(i)
 }
 
 /*
@@ -84,21 +63,23 @@ fn request_line<'a>(input: &'a [u8]) -> IResult<&'a[u8], Request<'a>> {
     version: http_version              >>
     line_ending                        >>
     ( Request {
-        method: method,
+        method,
         uri:    url,
-        version: version,
+        version,
     } )
   )
 }
 */
 
 fn request_line<'a>(i: &'a [u8]) -> IResult<&'a[u8], Request<'a>> {
-  let (i, method) = take_while1(i, is_token)?;
-  let (i, _) = take_while1(i, is_space)?;
-  let (i, uri) = take_while1(i, is_not_space)?;
-  let (i, _) = take_while1(i, is_space)?;
-  let (i, version) = http_version(i)?;
-  let (i, _) = line_ending(i)?;
+  let (i, (method, _, uri, _, version, _)) = tuple((
+    take_while1(TOKEN),
+    take_while1(is_space),
+    take_while1(is_not_space),
+    take_while1(is_space),
+    http_version,
+    line_ending,
+  ))(i)?;
 
   Ok((i, Request { method, uri, version }))
 }
@@ -111,8 +92,7 @@ named!(http_version, preceded!(
 */
 
 fn http_version(i: &[u8]) -> IResult<&[u8], &[u8]> {
-  let (i, _) = tag(&b"HTTP/"[..])(i)?;
-  take_while1(i, is_version)
+  preceded(tag(&b"HTTP/"[..]), take_while1(is_version))(i)
 }
 
 /*
@@ -124,11 +104,11 @@ named!(message_header_value, delimited!(
 */
 
 fn message_header_value(i: &[u8]) -> IResult<&[u8], &[u8]> {
-  delimited(i,
-    |i| take_while1(i, is_horizontal_space),
-    |i| take_while1(i, not_line_ending),
+  delimited(
+    take_while1(is_horizontal_space),
+    take_while1(not_line_ending),
     line_ending
-  )
+  )(i)
 }
 
 /*
@@ -139,17 +119,19 @@ fn message_header<'a>(input: &'a [u8]) -> IResult<&'a[u8], Header<'a>> {
     values: many1!(message_header_value) >>
 
     ( Header {
-        name: name,
+        name,
         value: values,
     } )
   )
 }
 */
 
-fn message_header(i: &[u8]) -> IResult<&[u8], Header> {
-  let (i, name) = take_while1(i, is_token)?;
-  let (i, _) = char(':')(i)?;
-  let (i, value) = many1(i, message_header_value)?;
+fn message_header<'a>(i: &'a [u8]) -> IResult<&'a[u8], Header<'a>> {
+  let (i, (name, _, value)) = tuple((
+    take_while1(TOKEN),
+    char(':'),
+    many1(message_header_value),
+  ))(i)?;
 
   Ok((i, Header { name, value }))
 }
@@ -163,11 +145,10 @@ fn request<'a>(input: &'a [u8]) -> IResult<&'a[u8], (Request<'a>, Vec<Header<'a>
 */
 
 fn request<'a>(i: &'a [u8]) -> IResult<&'a[u8], (Request<'a>, Vec<Header<'a>>)> {
-  let (i, request) = request_line(i)?;
-  let (i, headers) = many1(i, message_header)?;
-  let (i, _) = line_ending(i)?;
-
-  Ok((i, (request, headers)))
+  terminated(
+    pair(request_line, many1(message_header)),
+    line_ending
+  )(i)
 }
 
 fn small_test(b: &mut Bencher) {
@@ -1,6 +1,4 @@
 #[macro_use]
-extern crate nom;
-#[macro_use]
 extern crate bencher;
 extern crate nomfun;
 
@@ -10,8 +8,10 @@ use fnv::FnvHashMap as HashMap;
 use bencher::{Bencher, black_box};
 
 use nomfun::*;
+use nomfun::complete::{char, tag, take, take_while, take_while1};
 use std::fmt::Debug;
-use std::str::from_utf8;
+use std::str::{from_utf8, Utf8Error};
+use std::borrow::Cow;
 
 pub fn is_string_character(c: u8) -> bool {
   //FIXME: should validate unicode character
@@ -25,23 +25,24 @@ pub fn is_space(c: u8) -> bool {
 
 //named!(sp, take_while!(is_space));
 fn sp<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], &'a[u8], E> {
-  take_while(input, is_space)
+  take_while(is_space)(input)
 }
 
+#[allow(dead_code)]
 fn sp2<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], &'a[u8], E> {
-  let chars = b" \t\r\n";
+  const SPACE: ByteSet = ByteSet::from_bytes(b" \t\r\n");
 
-  take_while(input, |c| chars.contains(&c))
+  take_while(SPACE)(input)
 }
 
-// compat function because I don't want to rewrite nom::recognize_float just for this
-fn convert_rec_float<'a, E: Er<&'a [u8]>>(input: &'a [u8]) -> IResult<&'a [u8], &'a [u8], E> {
-  match nom::recognize_float(input) {
-    Ok((i, o)) => Ok((i, o)),
-    Err(nom::Err::Incomplete(_)) => Err(Err::Incomplete(Needed::Unknown)),
-    Err(nom::Err::Error(_)) => Err(Err::Error(E::from_error_kind(input, ErrorKind::ParseTo))),
-    Err(nom::Err::Failure(_)) => Err(Err::Failure(E::from_error_kind(input, ErrorKind::ParseTo))),
-  }
+// collects the characters a number can contain, the conversion to f64 in
+// `float` validates them. Optional fraction and exponent parsers would
+// allocate an error on every number with `Verbose`
+fn recognize_number<'a, E: Er<&'a [u8]>>(input: &'a [u8]) -> IResult<&'a [u8], &'a [u8], E> {
+  recognize(pair(
+    take_while1(|c: u8| c.is_ascii_digit() || c == b'-'),
+    take_while(|c: u8| c.is_ascii_digit() || b".eE+-".contains(&c)),
+  ))(input)
 }
 
 //named!(float<f64>, flat_map!(recognize_float, parse_to!(f64)));
@@ -54,99 +55,85 @@ fn float<'a, E: Er<&'a[u8]>>(i: &'a [u8]) -> IResult<&'a [u8], f64, E> {
     }
   };
 
-  flat_map(i, convert_rec_float, second)
+  flat_map(recognize_number, second)(i)
 }
 
 #[derive(Debug, PartialEq)]
 pub enum JsonValue<'a> {
-  Str(&'a str),
+  Str(Cow<'a, str>),
   Boolean(bool),
   Num(f64),
   Array(Vec<JsonValue<'a>>),
-  Object(HashMap<&'a str, JsonValue<'a>>),
-}
-
-use std::str;
-fn parse_str<'a, E:Er<&'a[u8]>>(input: &'a [u8]) -> IResult<&'a [u8], &'a str, E> {
-  // let's ignore escaping for now
-  /*
-  //println!("parse_str");
-  let res = map_res!(input,
-    escaped!(take_while1!(is_string_character), '\\', one_of!("\"bfnrt\\")),
-    str::from_utf8
-  );
-  //println!("parse_str({}) got {:?}", str::from_utf8(input).unwrap(), res);
-  res*/
-  let (i, data) = take_while(input, |c| c != b'"')?;
-  //println!("parse_str: data is {}", from_utf8(data).unwrap());
-  match from_utf8(data) {
-    Ok(s) => Ok((i, s)),
-    Err(_) => Err(Err::Error(E::from_error_kind(input, ErrorKind::ParseTo)))
+  Object(HashMap<Cow<'a, str>, JsonValue<'a>>),
+}
+
+fn escaped_char<'a, E: Er<&'a [u8]>>(input: &'a [u8]) -> IResult<&'a [u8], char, E> {
+  alt((
+    value(char('"'), '"'),
+    value(char('\\'), '\\'),
+    value(char('/'), '/'),
+    value(char('b'), '\u{8}'),
+    value(char('f'), '\u{c}'),
+    value(char('n'), '\n'),
+    value(char('r'), '\r'),
+    value(char('t'), '\t'),
+    // surrogate pairs are not supported
+    map_opt(preceded(char('u'), take(4usize)), |hex: &[u8]| {
+      from_utf8(hex).ok()
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .and_then(char::from_u32)
+    }),
+  ))(input)
+}
+
+fn cow_from_utf8(data: Cow<[u8]>) -> Result<Cow<str>, Utf8Error> {
+  match data {
+    Cow::Borrowed(data) => from_utf8(data).map(Cow::Borrowed),
+    Cow::Owned(data) => String::from_utf8(data).map(Cow::Owned).map_err(|e| e.utf8_error()),
   }
 }
 
-fn string<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], &'a str, E> {
+fn parse_str<'a, E:Er<&'a[u8]>>(input: &'a [u8]) -> IResult<&'a [u8], Cow<'a, str>, E> {
+  map_res_err(
+    escaped_transform(take_while(is_string_character), '\\', escaped_char),
+    cow_from_utf8
+  )(input)
+}
+
+fn string<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], Cow<'a, str>, E> {
   //println!("string");
-  let res = delimited(input, char('\"'), parse_str, char('\"'));
+  let res = delimited(char('\"'), parse_str, char('\"'))(input);
   //println!("string(\"{}\") returned {:?}", str::from_utf8(input).unwrap(), res);
   res
 }
 
 fn boolean<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], bool, E> {
   //println!("boolean");
-  or(input, &[
-   &|i| { value(i, tag(&b"false"[..]), false) },
-   &|i| { value(i, tag(&b"true"[..]), true) }
-  ])
+  alt((
+    value(tag(&b"false"[..]), false),
+    value(tag(&b"true"[..]), true),
+  ))(input)
 }
 
-fn array<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], Vec<JsonValue>, E> {
+fn array<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], Vec<JsonValue<'a>>, E> {
   //println!("array");
-  delimited(input,
+  delimited(
     char('['),
-    |i| separated_list(i, char(','), json_value),
-    char(']')
-  )
+    cut(separated_list0(char(','), json_value)),
+    cut(char(']'))
+  )(input)
 }
 
-fn key_value<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], (&'a str, JsonValue), E> {
+fn key_value<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], (Cow<'a, str>, JsonValue<'a>), E> {
   //println!("key_value");
-  let res = separated(input, string, char(':'), json_value);
+  let res = separated(string, char(':'), json_value)(input);
   //println!("key_value(\"{}\") returned {:?}", str::from_utf8(input).unwrap(), res);
   res
 }
 
-fn comma_kv<'a, E: Er<&'a [u8]>>(i: &'a[u8]) -> IResult<&'a [u8], (&'a str, JsonValue), E> {
-  let (i, _) = sp(i)?;
-  let (i, _) = char(',')(i)?;
-  key_value(i)
-}
-
-fn hash_internal<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], HashMap<&'a str, JsonValue>, E> {
+fn hash_internal<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], HashMap<Cow<'a, str>, JsonValue<'a>>, E> {
   //println!("hash_internal");
-  let res = match key_value(input) {
-    Err(Err::Error(_)) => Ok((input, HashMap::default())),
-    Err(e) => Err(e),
-    Ok((i, (key, value))) => {
-      let mut map = HashMap::default();
-      map.insert(key, value);
-
-      let mut input = i;
-      loop {
-        //match do_parse!(input, sp >> char!(',') >> kv: key_value >> (kv)) {
-        //match do_parse!(input, char!(',') >> kv: key_value >> (kv)) {
-        //match preceded(input, char(','), key_value) {
-        match comma_kv(input) {
-          Err(Err::Error(_)) => break Ok((input, map)),
-          Err(e) => break Err(e),
-          Ok((i, (key, value))) => {
-            map.insert(key, value);
-            input = i;
-          }
-        }
-      }
-    }
-  };
+  let res = separated_list0_into(preceded(sp, char(',')), key_value, HashMap::default)(input);
   //println!("hash_internal(\"{}\") returned {:?}", str::from_utf8(input).unwrap(), res);
   res
 
@@ -155,42 +142,42 @@ fn hash_internal<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], HashMa
 /*named!(
   hash<HashMap<&str, JsonValue>>,
 */
-fn hash<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], HashMap<&'a str, JsonValue>, E> {
-    let res = delimited(input,
+fn hash<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], HashMap<Cow<'a, str>, JsonValue<'a>>, E> {
+    let res = delimited(
       char('{'),
-      hash_internal,
+      cut(hash_internal),
       //preceded!(sp, char!('}'))
-      char('}')
-    );
+      cut(char('}'))
+    )(input);
     //println!("hash(\"{}\") returned {:?}", str::from_utf8(input).unwrap(), res);
     res
 }
 
-fn json_value<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], JsonValue, E> {
+fn json_value<'a, E: Er<&'a [u8]>>(input: &'a[u8]) -> IResult<&'a[u8], JsonValue<'a>, E> {
   //println!("json_value");
-  let res = or(input, &[
-   &|i| { map(i, string, JsonValue::Str) },
-   &|i| { map(i, float, JsonValue::Num) },
-   &|i| { map(i, array, JsonValue::Array) },
-   &|i| { map(i, hash, JsonValue::Object) },
-   &|i| { map(i, boolean, JsonValue::Boolean) },
-  ]);
+  let res = dispatch!(
+    b'"' => map(string, JsonValue::Str),
+    b'-' | b'0'..=b'9' => map(float, JsonValue::Num),
+    b'[' => map(array, JsonValue::Array),
+    b'{' => map(hash, JsonValue::Object),
+    b't' | b'f' => map(boolean, JsonValue::Boolean),
+  )(input);
   //println!("json_value({}) -> {:?}", str::from_utf8(input).unwrap(), res);
   res
 }
 
-fn root<'a, E: Er<&'a [u8]>>(input: &'a [u8]) -> IResult<&'a [u8], JsonValue, E> {
+fn root<'a, E: Er<&'a [u8]>>(input: &'a [u8]) -> IResult<&'a [u8], JsonValue<'a>, E> {
   //println!("root");
-  let res = or(input, &[
-   &|i| { map(i, array, JsonValue::Array) },
-   &|i| { map(i, hash, JsonValue::Object) },
-  ]);
+  let res = all_consuming(alt((
+    map(array, JsonValue::Array),
+    map(hash, JsonValue::Object),
+  )))(input);
   //println!("root({}) -> {:?}", str::from_utf8(input).unwrap(), res);
   res
 }
 
 fn basic(b: &mut Bencher) {
-  let data = b"{\"a\":42,\"b\":[\"x\",\"y\",12],\"c\":{\"hello\":\"world\"}};";
+  let data = b"{\"a\":42,\"b\":[\"x\",\"y\",12],\"c\":{\"hello\":\"world\"}}";
   //let data = b"{}";
 
   b.bytes = data.len() as u64;
@@ -198,7 +185,7 @@ fn basic(b: &mut Bencher) {
 }
 
 fn verbose(b: &mut Bencher) {
-  let data = b"{\"a\":42,\"b\":[\"x\",\"y\",12],\"c\":{\"hello\":\"world\"}};";
+  let data = b"{\"a\":42,\"b\":[\"x\",\"y\",12],\"c\":{\"hello\":\"world\"}}";
   //let data = b"{}";
 
   b.bytes = data.len() as u64;
@@ -211,11 +198,11 @@ fn parse<'a, E: Er<&'a[u8]>+Debug>(b: &mut Bencher, buffer: &'a[u8]) {
   assert!(res.is_ok());
 
   b.iter(|| {
-    let mut buf = black_box(buffer);
+    let buf = black_box(buffer);
     let res: IResult<_, _, E> = root(buf);
     match res {
-      Ok((i, o)) => {
-        return o;
+      Ok((_, o)) => {
+        o
       }
       Err(err) => {
         panic!("got parsing error: {:?}", err);
@@ -14,6 +14,7 @@ use nom::IResult;
 //use std::env;
 //use std::fs::File;
 
+#[allow(dead_code)]
 #[derive(Debug)]
 struct Request<'a> {
     method:  &'a [u8],
@@ -21,16 +22,18 @@ struct Request<'a> {
     version: &'a [u8],
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 struct Header<'a> {
     name:  &'a [u8],
     value: Vec<&'a [u8]>,
 }
 
+#[allow(clippy::match_like_matches_macro)]
 fn is_token(c: u8) -> bool {
     match c {
-        128...255 => false,
-        0...31    => false,
+        128..=255 => false,
+        0..=31    => false,
         b'('      => false,
         b')'      => false,
         b'<'      => false,
@@ -65,7 +68,7 @@ fn is_not_space(c: u8)        -> bool { c != b' ' }
 fn is_horizontal_space(c: u8) -> bool { c == b' ' || c == b'\t' }
 
 fn is_version(c: u8) -> bool {
-    c >= b'0' && c <= b'9' || c == b'.'
+    c.is_ascii_digit() || c == b'.'
 }
 
 named!(line_ending, alt!(tag!("\r\n") | tag!("\n")));
@@ -79,9 +82,9 @@ fn request_line<'a>(input: &'a [u8]) -> IResult<&'a[u8], Request<'a>> {
     version: http_version              >>
     line_ending                        >>
     ( Request {
-        method: method,
+        method,
         uri:    url,
-        version: version,
+        version,
     } )
   )
 }
@@ -104,7 +107,7 @@ fn message_header<'a>(input: &'a [u8]) -> IResult<&'a[u8], Header<'a>> {
     values: many1!(message_header_value) >>
 
     ( Header {
-        name: name,
+        name,
         value: values,
     } )
   )
@@ -0,0 +1,349 @@
+//! Choice between alternative parsers
+use crate::{IResult, Err, Needed, ErrorKind, Er, Parser};
+use crate::traits::{InputIter, InputIsPartial, InputLength};
+
+/// implemented for tuples of up to 21 parsers that have the same output
+/// type, see `alt`
+pub trait Alt<I, O, E> {
+  /// tries each parser in order, and returns the result of the first
+  /// one that does not return `Err::Error`
+  fn choice(&mut self, input: I) -> IResult<I, O, E>;
+}
+
+/// tries a list of parsers until one of them succeeds
+///
+/// `Failure` and `Incomplete` are returned right away. If every branch
+/// returned an error, their errors are combined with `Er::or`, so an error
+/// type like `Verbose` can keep the branch that went the farthest
+pub fn alt<I: Clone, O, E: Er<I>, List: Alt<I, O, E>>(mut l: List) -> impl FnMut(I) -> IResult<I, O, E> {
+  move |i: I| l.choice(i)
+}
+
+macro_rules! alt_trait(
+  ($first:ident $second:ident $($id: ident)+) => (
+    alt_trait!(__impl $first $second; $($id)+);
+  );
+  (__impl $($current:ident)*; $head:ident $($id: ident)+) => (
+    alt_trait_impl!($($current)*);
+
+    alt_trait!(__impl $($current)* $head; $($id)+);
+  );
+  (__impl $($current:ident)*; $head:ident) => (
+    alt_trait_impl!($($current)*);
+    alt_trait_impl!($($current)* $head);
+  );
+);
+
+macro_rules! alt_trait_impl(
+  ($($id:ident)+) => (
+    impl<
+      Input: Clone, Output, Error: Er<Input>,
+      $($id: Parser<Input, Output, Error>),+
+    > Alt<Input, Output, Error> for ( $($id),+ ) {
+
+      #[inline]
+      fn choice(&mut self, input: Input) -> IResult<Input, Output, Error> {
+        match self.0.parse(input.clone()) {
+          Err(Err::Error(e)) => alt_trait_inner!(1, self, input, e, $($id)+),
+          res => res,
+        }
+      }
+    }
+  );
+);
+
+macro_rules! alt_trait_inner(
+  ($it:tt, $self:expr, $input:expr, $err:expr, $head:ident $($id:ident)+) => (
+    match $self.$it.parse($input.clone()) {
+      Err(Err::Error(e)) => {
+        let err = $err.or(e);
+        succ!($it, alt_trait_inner!($self, $input, err, $($id)+))
+      }
+      res => res,
+    }
+  );
+  ($it:tt, $self:expr, $input:expr, $err:expr, $head:ident) => (
+    Err(Err::Error($err))
+  );
+);
+
+alt_trait!(A B C D E F G H I J K L M N O P Q R S T U);
+
+/// looks at the first element of the input, without consuming it, and
+/// calls `f` with it and the input, see `dispatch!`
+///
+/// returns an error on empty input, or `Incomplete(Needed::Size(1))` on a
+/// partial input
+pub fn dispatch<I, O, E: Er<I>, F>(mut f: F) -> impl FnMut(I) -> IResult<I, O, E>
+  where I: InputIter + InputIsPartial,
+        F: FnMut(<I as InputIter>::Item, I) -> IResult<I, O, E> {
+  move |i: I| {
+    match i.iter_elements().next() {
+      Some(c) => f(c, i),
+      None => if i.is_partial() {
+        Err(Err::Incomplete(Needed::Size(1)))
+      } else {
+        Err(Err::Error(E::from_error_kind(i, ErrorKind::Dispatch)))
+      },
+    }
+  }
+}
+
+/// chooses a parser from the first element of the input, which is not
+/// consumed, instead of trying each branch like `alt`:
+///
+/// ```no_run
+/// # use nomfun::*;
+/// # fn string(i: &[u8]) -> IResult<&[u8], u32> { unimplemented!() }
+/// # fn number(i: &[u8]) -> IResult<&[u8], u32> { unimplemented!() }
+/// # fn fallback(i: &[u8]) -> IResult<&[u8], u32> { unimplemented!() }
+/// # fn json_value(input: &[u8]) -> IResult<&[u8], u32> {
+/// dispatch!(
+///   b'"' => string,
+///   b'-' | b'0'..=b'9' => number,
+///   _ => fallback,
+/// )(input)
+/// # }
+/// ```
+///
+/// the error of the chosen parser is returned as is. Without a `_` arm,
+/// an element that no pattern matches returns an `ErrorKind::Dispatch`
+/// error, like empty input does, see `dispatch`
+#[macro_export]
+macro_rules! dispatch(
+  ($($($pat:pat)|+ => $parser:expr),+ $(,)?) => (
+    $crate::dispatch(move |c, input| {
+      #[allow(unreachable_patterns)]
+      let res = match c {
+        $($($pat)|+ => $crate::Parser::parse(&mut $parser, input),)+
+        _ => Err($crate::Err::Error($crate::Er::from_error_kind(input, $crate::ErrorKind::Dispatch))),
+      };
+      res
+    })
+  );
+);
+
+/// implemented for tuples of 2 to 21 parsers, see `permutation`
+pub trait Permutation<I, O, E> {
+  type Output;
+
+  /// applies the parsers in any order until each one matched once
+  fn permutation(&mut self, input: I) -> IResult<I, Self::Output, E>;
+}
+
+/// applies a tuple of parsers in any order, until each of them matched
+/// exactly once, and returns their results in the order of the tuple:
+/// `permutation((header_a, opt(header_b), header_c))`
+///
+/// every round, the parsers that did not match yet are tried in order on
+/// the remaining input. A parser that succeeds without consuming anything,
+/// like `opt` on a missing element, is tried again in the next rounds and
+/// only keeps its empty result if nothing else matched, so optional members
+/// can appear anywhere. When a round makes no progress and a parser never
+/// succeeded, the errors of that round are combined with `Er::or`.
+/// `Failure` and `Incomplete` are returned right away
+pub fn permutation<I: Clone + InputLength, O, E: Er<I>, List: Permutation<I, O, E>>(mut l: List) -> impl FnMut(I) -> IResult<I, List::Output, E> {
+  move |i: I| l.permutation(i)
+}
+
+macro_rules! permutation_trait(
+  ($name1:ident $ty1:ident $p1:ident $r1:ident, $name2:ident $ty2:ident $p2:ident $r2:ident, $($name:ident $ty:ident $p:ident $r:ident),*) => (
+    permutation_trait!(__impl $name1 $ty1 $p1 $r1, $name2 $ty2 $p2 $r2; $($name $ty $p $r),*);
+  );
+  (__impl $($name:ident $ty:ident $p:ident $r:ident),+; $name1:ident $ty1:ident $p1:ident $r1:ident, $($name2:ident $ty2:ident $p2:ident $r2:ident),*) => (
+    permutation_trait_impl!($($name $ty $p $r),+);
+    permutation_trait!(__impl $($name $ty $p $r),+ , $name1 $ty1 $p1 $r1; $($name2 $ty2 $p2 $r2),*);
+  );
+  (__impl $($name:ident $ty:ident $p:ident $r:ident),+; $name1:ident $ty1:ident $p1:ident $r1:ident) => (
+    permutation_trait_impl!($($name $ty $p $r),+);
+    permutation_trait_impl!($($name $ty $p $r),+, $name1 $ty1 $p1 $r1);
+  );
+);
+
+macro_rules! permutation_trait_impl(
+  ($($name:ident $ty:ident $p:ident $r:ident),+) => (
+    impl<
+      Input: Clone + InputLength, $($ty),+ , Error: Er<Input>,
+      $($name: Parser<Input, $ty, Error>),+
+    > Permutation<Input, ( $($ty),+ ), Error> for ( $($name),+ ) {
+      type Output = ( $($ty),+ );
+
+      fn permutation(&mut self, input: Input) -> IResult<Input, Self::Output, Error> {
+        let ( $(ref mut $p),+ ) = *self;
+        let mut input = input;
+        // `Some((o, false))` for a parser that only matched without
+        // consuming anything, it is tried again in the next rounds
+        $(let mut $r: Option<($ty, bool)> = None;)+
+        let mut err: Option<Error>;
+
+        loop {
+          let mut matched = false;
+          err = None;
+
+          $(
+            if !matches!($r, Some((_, true))) {
+              match $p.parse(input.clone()) {
+                Ok((i, o)) => {
+                  let consumed = i.input_len() < input.input_len();
+                  if consumed {
+                    input = i;
+                    matched = true;
+                  }
+                  $r = Some((o, consumed));
+                },
+                Err(Err::Error(e)) => if $r.is_none() {
+                  err = Some(match err {
+                    Some(err) => err.or(e),
+                    None => e,
+                  });
+                },
+                Err(e) => return Err(e),
+              }
+            }
+          )+
+
+          if !matched {
+            break;
+          }
+        }
+
+        $(
+          let $r = match $r {
+            Some((o, _)) => o,
+            None => {
+              // this parser failed in the last round, so its error was
+              // recorded
+              let e = err.unwrap_or_else(|| Error::from_error_kind(input, ErrorKind::Permutation));
+              return Err(Err::Error(e));
+            },
+          };
+        )+
+
+        Ok((input, ( $($r),+ )))
+      }
+    }
+  );
+);
+
+permutation_trait!(
+  FnA A pa ra, FnB B pb rb, FnC C pc rc, FnD D pd rd, FnE E pe re,
+  FnF F pf rf, FnG G pg rg, FnH H ph rh, FnI I pi ri, FnJ J pj rj,
+  FnK K pk rk, FnL L pl rl, FnM M pm rm, FnN N pn rn, FnO O po ro,
+  FnP P pp rp, FnQ Q pq rq, FnR R pr rr, FnS S ps rs, FnT T pt rt,
+  FnU U pu ru
+);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{char, cut, opt, pair, tag, BoxedParser, Partial, Verbose};
+  use crate::test_util::{R, trace};
+
+  #[test]
+  fn alt_keeps_farthest_error() {
+    let r: IResult<&str, char, Verbose<&str>> = alt((pair(char('a'), char('b')).map(|(_, b)| b), char('c')))("ax");
+    match r {
+      Err(Err::Error(e)) => assert_eq!(trace(&e), vec![("x", "E(Char)".to_string())]),
+      r => panic!("unexpected {:?}", r),
+    }
+    let r: IResult<&str, char, Verbose<&str>> = alt((char('c'), pair(char('a'), char('b')).map(|(_, b)| b), char('d')))("ax");
+    match r {
+      Err(Err::Error(e)) => assert_eq!(trace(&e), vec![("x", "E(Char)".to_string())]),
+      r => panic!("unexpected {:?}", r),
+    }
+  }
+
+  #[test]
+  fn alt_failure_and_incomplete() {
+    let r: R<char> = alt((cut(char('a')), char('b')))(&b"b"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"b"[..], 0))));
+    let r: R<char> = alt((pair(char('a'), cut(char('b'))).map(|(_, b)| b), char('a')))(&b"ax"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"x"[..], 0))));
+
+    let r: IResult<Partial<&[u8]>, char> = alt((char('a'), char('b')))(Partial::new(&b""[..]));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    // the first branch needs more data, the second one is not tried
+    let r: IResult<Partial<&[u8]>, Partial<&[u8]>> = alt((tag("ab"), tag("a")))(Partial::new(&b"a"[..]));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+  }
+
+  #[test]
+  fn alt_21_branches() {
+    let mut p = alt((
+      char('a'), char('b'), char('c'), char('d'), char('e'), char('f'), char('g'),
+      char('h'), char('i'), char('j'), char('k'), char('l'), char('m'), char('n'),
+      char('o'), char('p'), char('q'), char('r'), char('s'), char('t'), char('u'),
+    ));
+    let r: R<char> = p(&b"ax"[..]);
+    assert_eq!(r, Ok((&b"x"[..], 'a')));
+    let r: R<char> = p(&b"ux"[..]);
+    assert_eq!(r, Ok((&b"x"[..], 'u')));
+    let r: R<char> = p(&b"vx"[..]);
+    assert_eq!(r, Err(Err::Error((&b"vx"[..], 0))));
+  }
+
+  #[test]
+  fn permutation_any_order() {
+    let r: R<(char, char, char)> = permutation((char('a'), char('b'), char('c')))(&b"cabx"[..]);
+    assert_eq!(r, Ok((&b"x"[..], ('a', 'b', 'c'))));
+    let r: R<(char, char, char)> = permutation((char('a'), char('b'), char('c')))(&b"cax"[..]);
+    assert_eq!(r, Err(Err::Error((&b"x"[..], 0))));
+  }
+
+  #[test]
+  fn permutation_optional_members() {
+    let r: R<(char, Option<char>, char)> = permutation((char('a'), opt(char('b')), char('c')))(&b"cbax"[..]);
+    assert_eq!(r, Ok((&b"x"[..], ('a', Some('b'), 'c'))));
+    let r: R<(char, Option<char>, char)> = permutation((char('a'), opt(char('b')), char('c')))(&b"cax"[..]);
+    assert_eq!(r, Ok((&b"x"[..], ('a', None, 'c'))));
+    let r: R<(char, Option<char>, char)> = permutation((char('a'), opt(char('b')), char('c')))(&b"bx"[..]);
+    assert_eq!(r, Err(Err::Error((&b"x"[..], 0))));
+  }
+
+  #[test]
+  fn permutation_parser_types() {
+    let boxed: BoxedParser<&[u8], char, (&[u8], u32)> = char('a').boxed();
+    let r: R<(char, char, char)> = permutation((boxed, char('b').map(|c| c), char('c').or(char('d'))))(&b"dbax"[..]);
+    assert_eq!(r, Ok((&b"x"[..], ('a', 'b', 'd'))));
+  }
+
+  fn keyword<'a, E: Er<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
+    dispatch!(
+      'a' => tag("and"),
+      'o' | 'x' => alt((tag("or"), tag("xor"))),
+    )(i)
+  }
+
+  #[test]
+  fn dispatch_outcomes() {
+    let r: IResult<&str, &str> = keyword("and b");
+    assert_eq!(r, Ok((" b", "and")));
+    let r: IResult<&str, &str> = keyword("xor b");
+    assert_eq!(r, Ok((" b", "xor")));
+
+    // the chosen parser's error is returned as is
+    let r: IResult<&str, &str, Verbose<&str>> = keyword("as");
+    match r {
+      Err(Err::Error(e)) => assert_eq!(trace(&e), vec![("as", "E(Tag)".to_string())]),
+      r => panic!("unexpected {:?}", r),
+    }
+    let r: IResult<&str, &str, Verbose<&str>> = keyword("not");
+    match r {
+      Err(Err::Error(e)) => assert_eq!(trace(&e), vec![("not", "E(Dispatch)".to_string())]),
+      r => panic!("unexpected {:?}", r),
+    }
+    let r: IResult<&str, &str, Verbose<&str>> = keyword("");
+    match r {
+      Err(Err::Error(e)) => assert_eq!(trace(&e), vec![("", "E(Dispatch)".to_string())]),
+      r => panic!("unexpected {:?}", r),
+    }
+
+    let mut p = dispatch!(b'a' => tag("ab"), _ => tag("c"));
+    let r: IResult<Partial<&[u8]>, Partial<&[u8]>> = p(Partial::new(&b""[..]));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: IResult<Partial<&[u8]>, Partial<&[u8]>> = p(Partial::new(&b"a"[..]));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: IResult<Partial<&[u8]>, Partial<&[u8]>> = p(Partial::new(&b"cd"[..]));
+    assert_eq!(r, Ok((Partial::new(&b"d"[..]), Partial::new(&b"c"[..]))));
+  }
+}
@@ -0,0 +1,288 @@
+//! Primitives for data that is entirely in memory
+//!
+//! These never return `Err::Incomplete`: reaching the end of the input is
+//! treated like any other mismatch.
+use crate::{IResult, Err, ErrorKind, Er, Parser};
+use crate::traits::*;
+
+/// recognizes a single character. The character is matched as a whole
+/// code point: on `&[u8]` its UTF-8 encoding is compared, so `char('é')`
+/// consumes two bytes
+///
+/// returns an error if the input is empty or too short
+//#[inline(always)]
+pub fn char<I, E: Er<I>>(c: char) -> impl Fn(I) -> IResult<I, char, E>
+  where I: for<'b> Compare<&'b [u8]> + InputTake {
+
+  let mut buf = [0; 4];
+  let len = c.encode_utf8(&mut buf).len();
+
+  move |i: I| {
+    match i.compare(&buf[..len]) {
+      CompareResult::Ok => Ok((i.take_split(len).0, c)),
+      CompareResult::Incomplete | CompareResult::Error => {
+        Err(Err::Error(E::from_error_kind(i, ErrorKind::Char)))
+      }
+    }
+  }
+}
+
+/// recognizes a fixed sequence, like `tag(&b"HTTP/"[..])` or `tag("GET")`.
+/// A `&str` tag can be used on both `&str` and `&[u8]` input
+///
+/// returns an error if the input is shorter than the tag
+pub fn tag<T, I, E: Er<I>>(t: T) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTake + Compare<T>,
+        T: InputLength + Clone {
+  move |i: I| {
+    let tag_len = t.input_len();
+    match i.compare(t.clone()) {
+      CompareResult::Ok => Ok(i.take_split(tag_len)),
+      CompareResult::Incomplete | CompareResult::Error => {
+        Err(Err::Error(E::from_error_kind(i, ErrorKind::Tag)))
+      }
+    }
+  }
+}
+
+/// like `tag`, but ignores case: ASCII case on `&[u8]` input, Unicode
+/// simple case folding on `&str` input, where the matched part can have a
+/// different length than the tag
+///
+/// returns an error if the input is shorter than the tag
+pub fn tag_no_case<T, I, E: Er<I>>(t: T) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTake + CompareNoCase<T>,
+        T: Clone {
+  move |i: I| {
+    match i.compare_no_case(t.clone()) {
+      (CompareResult::Ok, len) => Ok(i.take_split(len)),
+      (CompareResult::Incomplete, _) | (CompareResult::Error, _) => {
+        Err(Err::Error(E::from_error_kind(i, ErrorKind::Tag)))
+      }
+    }
+  }
+}
+
+/// returns the longest prefix whose elements match the predicate. On `&str`
+/// the predicate is called on each `char`, on `&[u8]` on each byte
+///
+/// consumes the whole input if every element matched
+pub fn take_while<I, F, E: Er<I>>(cond: F) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTakeAtPosition,
+        F: Predicate<<I as InputTakeAtPosition>::Item> {
+  move |input: I| {
+    Ok(input.split_at_position_complete(|c| !cond.test(c)))
+  }
+}
+
+/// like `take_while`, but fails if the first element does not match
+///
+/// returns an error on empty input, consumes the whole input if every
+/// element matched
+//#[inline(always)]
+pub fn take_while1<I, F, E: Er<I>>(cond: F) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTakeAtPosition + InputLength,
+        F: Predicate<<I as InputTakeAtPosition>::Item> {
+  move |input: I| {
+    let (i, o) = input.split_at_position_complete(|c| !cond.test(c));
+    if o.input_len() == 0 {
+      Err(Err::Error(E::from_error_kind(input, ErrorKind::TakeWhile1)))
+    } else {
+      Ok((i, o))
+    }
+  }
+}
+
+/// returns the first `count` elements of the input. On `&str`, elements
+/// are chars
+///
+/// returns an error if the input is too short
+pub fn take<I, E: Er<I>>(count: usize) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputIter + InputTake {
+  move |i: I| {
+    match i.slice_index(count) {
+      Some(index) => Ok(i.take_split(index)),
+      None => Err(Err::Error(E::from_error_kind(i, ErrorKind::Take))),
+    }
+  }
+}
+
+/// returns the input up to the first occurrence of `t`, which is not
+/// consumed
+///
+/// returns an error if `t` was not found
+pub fn take_until<T, I, E: Er<I>>(t: T) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTake + FindSubstring<T>,
+        T: Clone {
+  move |i: I| {
+    match i.find_substring(t.clone()) {
+      Some(index) => Ok(i.take_split(index)),
+      None => Err(Err::Error(E::from_error_kind(i, ErrorKind::TakeUntil))),
+    }
+  }
+}
+
+/// returns the longest prefix whose elements do not match the predicate
+///
+/// consumes the whole input if no element matched
+pub fn take_till<I, F, E: Er<I>>(cond: F) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTakeAtPosition,
+        F: Predicate<<I as InputTakeAtPosition>::Item> {
+  move |input: I| {
+    Ok(input.split_at_position_complete(|c| cond.test(c)))
+  }
+}
+
+/// gets a length from the first parser, then returns that many elements
+///
+/// returns an error if the input is too short
+pub fn length_data<I, N, E: Er<I>, F>(mut f: F) -> impl FnMut(I) -> IResult<I, I, E>
+  where I: InputIter + InputTake,
+        N: ToUsize,
+        F: Parser<I, N, E> {
+  move |i: I| {
+    let (i, length) = f.parse(i)?;
+    take(length.to_usize())(i)
+  }
+}
+
+/// gets a length from the first parser, then applies the second one on
+/// that many elements. The second parser does not have to consume all of
+/// them
+///
+/// returns an error if the input is too short
+pub fn length_value<I, N, O, E: Er<I>, F, G>(mut f: F, mut g: G) -> impl FnMut(I) -> IResult<I, O, E>
+  where I: InputIter + InputTake,
+        N: ToUsize,
+        F: Parser<I, N, E>,
+        G: Parser<I, O, E> {
+  move |i: I| {
+    let (i, data) = length_data(|i| f.parse(i))(i)?;
+    let (_, o) = g.parse(data)?;
+    Ok((i, o))
+  }
+}
+
+/// recognizes one element that is in the set, like
+/// `one_of(ByteSet::from_bytes(b"+-"))` or `one_of(|c: char| c.is_alphabetic())`
+///
+/// returns an error on empty input
+pub fn one_of<I, P, E: Er<I>>(set: P) -> impl Fn(I) -> IResult<I, <I as InputIter>::Item, E>
+  where I: InputIter + InputTake,
+        <I as InputIter>::Item: Copy,
+        P: Predicate<<I as InputIter>::Item> {
+  move |i: I| one_element(i, |c| set.test(c), ErrorKind::OneOf)
+}
+
+/// recognizes one element that is not in the set
+///
+/// returns an error on empty input
+pub fn none_of<I, P, E: Er<I>>(set: P) -> impl Fn(I) -> IResult<I, <I as InputIter>::Item, E>
+  where I: InputIter + InputTake,
+        <I as InputIter>::Item: Copy,
+        P: Predicate<<I as InputIter>::Item> {
+  move |i: I| one_element(i, |c| !set.test(c), ErrorKind::NoneOf)
+}
+
+/// recognizes the first element if `accept` takes it, shared by the
+/// `one_of` and `none_of` variants
+#[inline]
+pub(crate) fn one_element<I, E: Er<I>, F>(i: I, accept: F, kind: ErrorKind) -> IResult<I, <I as InputIter>::Item, E>
+  where I: InputIter + InputTake,
+        <I as InputIter>::Item: Copy,
+        F: Fn(<I as InputIter>::Item) -> bool {
+  match i.iter_elements().next() {
+    Some(c) if accept(c) => {
+      // `c` is there, so is the position after it
+      let index = i.slice_index(1).unwrap();
+      Ok((i.take_split(index).0, c))
+    },
+    _ => Err(Err::Error(E::from_error_kind(i, kind))),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_util::{R, len_u8};
+
+  #[test]
+  fn char_end_of_input() {
+    let r: R<char> = char('a')(&b""[..]);
+    assert_eq!(r, Err(Err::Error((&b""[..], 0))));
+    let r: R<char> = char('é')(&b"\xc3"[..]);
+    assert_eq!(r, Err(Err::Error((&b"\xc3"[..], 0))));
+    let r: IResult<&str, char> = char('a')("");
+    assert_eq!(r, Err(Err::Error(("", 0))));
+    let r: IResult<&str, char> = char('é')("éa");
+    assert_eq!(r, Ok(("a", 'é')));
+  }
+
+  #[test]
+  fn tag_end_of_input() {
+    let r: R<&[u8]> = tag("HTTP/")(&b""[..]);
+    assert_eq!(r, Err(Err::Error((&b""[..], 0))));
+    let r: R<&[u8]> = tag("HTTP/")(&b"HT"[..]);
+    assert_eq!(r, Err(Err::Error((&b"HT"[..], 0))));
+    let r: R<&[u8]> = tag("é")(&b"\xc3"[..]);
+    assert_eq!(r, Err(Err::Error((&b"\xc3"[..], 0))));
+    let r: IResult<&str, &str> = tag("HTTP/")("HTTP/1.1");
+    assert_eq!(r, Ok(("1.1", "HTTP/")));
+
+    let r: R<&[u8]> = tag_no_case("get")(&b"G"[..]);
+    assert_eq!(r, Err(Err::Error((&b"G"[..], 0))));
+    let r: IResult<&str, &str> = tag_no_case("get")("gEt");
+    assert_eq!(r, Ok(("", "gEt")));
+  }
+
+  #[test]
+  fn take_while_end_of_input() {
+    let r: R<&[u8]> = take_while(|c: u8| c == b'a')(&b""[..]);
+    assert_eq!(r, Ok((&b""[..], &b""[..])));
+    let r: R<&[u8]> = take_while(|c: u8| c == b'a')(&b"aa"[..]);
+    assert_eq!(r, Ok((&b""[..], &b"aa"[..])));
+
+    let r: R<&[u8]> = take_while1(|c: u8| c == b'a')(&b""[..]);
+    assert_eq!(r, Err(Err::Error((&b""[..], 0))));
+    let r: R<&[u8]> = take_while1(|c: u8| c == b'a')(&b"aa"[..]);
+    assert_eq!(r, Ok((&b""[..], &b"aa"[..])));
+
+    let r: R<&[u8]> = take_till(|c: u8| c == b';')(&b"ab"[..]);
+    assert_eq!(r, Ok((&b""[..], &b"ab"[..])));
+  }
+
+  #[test]
+  fn take_end_of_input() {
+    let r: R<&[u8]> = take(4)(&b""[..]);
+    assert_eq!(r, Err(Err::Error((&b""[..], 0))));
+    let r: R<&[u8]> = take(4)(&b"ab"[..]);
+    assert_eq!(r, Err(Err::Error((&b"ab"[..], 0))));
+    let r: IResult<&str, &str> = take(1)("éa");
+    assert_eq!(r, Ok(("a", "é")));
+
+    let r: R<&[u8]> = take_until("\r\n")(&b"abc\r"[..]);
+    assert_eq!(r, Err(Err::Error((&b"abc\r"[..], 0))));
+    let r: R<&[u8]> = take_until("\r\n")(&b"abc\r\n"[..]);
+    assert_eq!(r, Ok((&b"\r\n"[..], &b"abc"[..])));
+  }
+
+  #[test]
+  fn length_end_of_input() {
+    let r: R<&[u8]> = length_data(len_u8)(&b""[..]);
+    assert_eq!(r, Err(Err::Error((&b""[..], 0))));
+    let r: R<&[u8]> = length_data(len_u8)(&b"\x03a"[..]);
+    assert_eq!(r, Err(Err::Error((&b"a"[..], 0))));
+    let r: R<&[u8]> = length_value(len_u8, tag("ab"))(&b"\x03abcd"[..]);
+    assert_eq!(r, Ok((&b"d"[..], &b"ab"[..])));
+  }
+
+  #[test]
+  fn one_of_end_of_input() {
+    let r: R<u8> = one_of(|c: u8| c == b'a')(&b""[..]);
+    assert_eq!(r, Err(Err::Error((&b""[..], 0))));
+    let r: R<u8> = none_of(|c: u8| c == b'a')(&b""[..]);
+    assert_eq!(r, Err(Err::Error((&b""[..], 0))));
+    let r: IResult<&str, char> = one_of(|c: char| c.is_alphabetic())("éa");
+    assert_eq!(r, Ok(("a", 'é')));
+  }
+}
@@ -0,0 +1,185 @@
+//! Strings containing escape sequences
+use std::borrow::Cow;
+use std::ops::{RangeFrom, RangeTo};
+
+use crate::{IResult, Err, Needed, ErrorKind, Er, Parser};
+use crate::traits::*;
+
+/// recognizes a sequence of `normal` parts and escapes, where an escape is
+/// `control_char` followed by something `escapable` accepts, and returns
+/// the consumed input without copying it:
+/// `escaped(take_while1(is_alpha), '\\', alt((char('"'), char('n'))))`
+///
+/// stops at the first element that is neither, which can result in an
+/// empty match. A `normal` parser matching an empty sequence is not an
+/// error. On a partial input, reaching the end of the data returns
+/// `Incomplete`
+pub fn escaped<I, O1, O2, E: Er<I>, F, G>(mut normal: F, control_char: char, mut escapable: G) -> impl FnMut(I) -> IResult<I, I, E>
+  where I: Clone + Offset + InputLength + InputIsPartial + InputIter + Slice<RangeFrom<usize>> + Slice<RangeTo<usize>>,
+        <I as InputIter>::Item: AsChar,
+        F: Parser<I, O1, E>,
+        G: Parser<I, O2, E> {
+
+  move |input: I| {
+    let mut i = input.clone();
+
+    loop {
+      if i.input_len() == 0 {
+        if i.is_partial() {
+          return Err(Err::Incomplete(Needed::Unknown));
+        }
+        break;
+      }
+
+      match normal.parse(i.clone()) {
+        Ok((i2, _)) if i2.input_len() < i.input_len() => {
+          i = i2;
+          continue;
+        },
+        Ok(_) | Err(Err::Error(_)) => {},
+        Err(e) => return Err(e),
+      }
+
+      let i2 = escape_start(&i, control_char, ErrorKind::Escaped)?;
+      match i2 {
+        None => break,
+        Some(i2) => {
+          let (i2, _) = escapable.parse(i2)?;
+          i = i2;
+        },
+      }
+    }
+
+    // `i` carries what the parsers changed in a `Stateful` input
+    let index = input.offset(&i);
+    Ok((i, input.slice(..index)))
+  }
+}
+
+/// like `escaped`, but also converts the escapes with `transform` and
+/// returns the unescaped data: `Cow<str>` on `&str` input, `Cow<[u8]>` on
+/// `&[u8]` input
+///
+/// the result borrows the input if it contains no escape, otherwise the
+/// `normal` parts and the results of `transform` are copied to a new
+/// `String` or `Vec<u8>`
+pub fn escaped_transform<'a, I, O1, O2, C, E: Er<I>, F, G>(mut normal: F, control_char: char, mut transform: G) -> impl FnMut(I) -> IResult<I, Cow<'a, I::Target>, E>
+  where I: Clone + Offset + InputLength + InputIsPartial + InputIter + Slice<RangeFrom<usize>> + Slice<RangeTo<usize>> + ToCow<'a> + ExtendInto<C>,
+        I::Target: ToOwned<Owned = C>,
+        <I as InputIter>::Item: AsChar,
+        C: Default,
+        O2: ExtendInto<C>,
+        F: Parser<I, O1, E>,
+        G: Parser<I, O2, E> {
+
+  move |input: I| {
+    let mut i = input.clone();
+    // only allocated once the first escape is found
+    let mut acc: Option<C> = None;
+
+    loop {
+      if i.input_len() == 0 {
+        if i.is_partial() {
+          return Err(Err::Incomplete(Needed::Unknown));
+        }
+        break;
+      }
+
+      match normal.parse(i.clone()) {
+        Ok((i2, _)) if i2.input_len() < i.input_len() => {
+          if let Some(acc) = acc.as_mut() {
+            i.slice(..i.offset(&i2)).extend_into(acc);
+          }
+          i = i2;
+          continue;
+        },
+        Ok(_) | Err(Err::Error(_)) => {},
+        Err(e) => return Err(e),
+      }
+
+      let i2 = escape_start(&i, control_char, ErrorKind::EscapedTransform)?;
+      match i2 {
+        None => break,
+        Some(i2) => {
+          let (i2, o) = transform.parse(i2)?;
+          let acc = acc.get_or_insert_with(|| {
+            let mut acc = C::default();
+            input.slice(..input.offset(&i)).extend_into(&mut acc);
+            acc
+          });
+          o.extend_into(acc);
+          i = i2;
+        },
+      }
+    }
+
+    match acc {
+      Some(acc) => Ok((i, Cow::Owned(acc))),
+      None => {
+        let index = input.offset(&i);
+        Ok((i, input.slice(..index).to_cow()))
+      },
+    }
+  }
+}
+
+// returns the input after `control_char` if it starts with it, or `None`
+fn escape_start<I, E: Er<I>>(i: &I, control_char: char, kind: ErrorKind) -> Result<Option<I>, Err<E>>
+  where I: Clone + InputLength + InputIsPartial + InputIter + Slice<RangeFrom<usize>>,
+        <I as InputIter>::Item: AsChar {
+
+  match i.iter_elements().next() {
+    Some(c) if c.to_char() == control_char => {},
+    _ => return Ok(None),
+  }
+
+  let next = control_char.len_utf8();
+  if next >= i.input_len() {
+    if i.is_partial() {
+      return Err(Err::Incomplete(Needed::Size(1)));
+    }
+    return Err(Err::Error(E::from_error_kind(i.clone(), kind)));
+  }
+
+  Ok(Some(i.slice(next..)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{complete, alt, ByteSet, Partial};
+
+  type P<'a> = Partial<&'a [u8]>;
+
+  const PLAIN: ByteSet = ByteSet::from_bytes(b"\\\"").complement();
+
+  #[test]
+  fn escaped_one_element_normal() {
+    let r: IResult<&[u8], &[u8]> = escaped(complete::one_of(PLAIN), '\\', complete::one_of(ByteSet::from_bytes(b"\"n\\")))(&b"abc\\n\"rest"[..]);
+    assert_eq!(r, Ok((&b"\"rest"[..], &b"abc\\n"[..])));
+    let r: IResult<&str, &str> = escaped(complete::one_of(PLAIN), '\\', complete::one_of(ByteSet::from_bytes(b"\"n\\")))("a\\\"bc\"");
+    assert_eq!(r, Ok(("\"", "a\\\"bc")));
+  }
+
+  #[test]
+  fn escaped_transform_one_element_normal() {
+    let unescape = alt((complete::char('n').map(|_| '\n'), complete::char('"')));
+
+    let r: IResult<&[u8], Cow<[u8]>> = escaped_transform(complete::one_of(PLAIN), '\\', unescape)(&b"abc\""[..]);
+    assert_eq!(r, Ok((&b"\""[..], Cow::Borrowed(&b"abc"[..]))));
+
+    let unescape = alt((complete::char('n').map(|_| '\n'), complete::char('"')));
+    let r: IResult<&str, Cow<str>> = escaped_transform(complete::one_of(PLAIN), '\\', unescape)("ab\\ncd\"");
+    assert_eq!(r, Ok(("\"", Cow::Owned("ab\ncd".to_string()))));
+  }
+
+  #[test]
+  fn escaped_end_of_input() {
+    let r: IResult<&[u8], &[u8]> = escaped(complete::one_of(PLAIN), '\\', complete::char('n'))(&b"ab\\"[..]);
+    assert_eq!(r, Err(Err::Error((&b"\\"[..], 0))));
+    let r: IResult<P, P> = escaped(crate::one_of(PLAIN), '\\', crate::char('n'))(Partial::new(&b"ab\\"[..]));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: IResult<P, P> = escaped(crate::one_of(PLAIN), '\\', crate::char('n'))(Partial::new(&b"ab"[..]));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Unknown)));
+  }
+}
@@ -1,27 +1,67 @@
-#[macro_use]
-extern crate nom;
-#[macro_use]
-extern crate bencher;
-
-extern crate fnv;
-
-use fnv::FnvHashMap as HashMap;
-use bencher::{Bencher, black_box};
-
-use nom::{digit, be_u32, InputTakeAtPosition, Convert, recognize_float,
-  ParseTo, Slice, InputLength, HexDisplay, InputTake, Compare, CompareResult, need_more,};
-
-use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::ops::RangeTo;
+
+/// increments a tuple index in macro calls
+macro_rules! succ (
+  (0, $submac:ident ! ($($rest:tt)*)) => ($submac!(1, $($rest)*));
+  (1, $submac:ident ! ($($rest:tt)*)) => ($submac!(2, $($rest)*));
+  (2, $submac:ident ! ($($rest:tt)*)) => ($submac!(3, $($rest)*));
+  (3, $submac:ident ! ($($rest:tt)*)) => ($submac!(4, $($rest)*));
+  (4, $submac:ident ! ($($rest:tt)*)) => ($submac!(5, $($rest)*));
+  (5, $submac:ident ! ($($rest:tt)*)) => ($submac!(6, $($rest)*));
+  (6, $submac:ident ! ($($rest:tt)*)) => ($submac!(7, $($rest)*));
+  (7, $submac:ident ! ($($rest:tt)*)) => ($submac!(8, $($rest)*));
+  (8, $submac:ident ! ($($rest:tt)*)) => ($submac!(9, $($rest)*));
+  (9, $submac:ident ! ($($rest:tt)*)) => ($submac!(10, $($rest)*));
+  (10, $submac:ident ! ($($rest:tt)*)) => ($submac!(11, $($rest)*));
+  (11, $submac:ident ! ($($rest:tt)*)) => ($submac!(12, $($rest)*));
+  (12, $submac:ident ! ($($rest:tt)*)) => ($submac!(13, $($rest)*));
+  (13, $submac:ident ! ($($rest:tt)*)) => ($submac!(14, $($rest)*));
+  (14, $submac:ident ! ($($rest:tt)*)) => ($submac!(15, $($rest)*));
+  (15, $submac:ident ! ($($rest:tt)*)) => ($submac!(16, $($rest)*));
+  (16, $submac:ident ! ($($rest:tt)*)) => ($submac!(17, $($rest)*));
+  (17, $submac:ident ! ($($rest:tt)*)) => ($submac!(18, $($rest)*));
+  (18, $submac:ident ! ($($rest:tt)*)) => ($submac!(19, $($rest)*));
+  (19, $submac:ident ! ($($rest:tt)*)) => ($submac!(20, $($rest)*));
+  (20, $submac:ident ! ($($rest:tt)*)) => ($submac!(21, $($rest)*));
+);
+
+pub mod traits;
+pub mod streaming;
+pub mod complete;
+mod partial;
+mod span;
+mod branch;
+mod sequence;
+mod escape;
+mod set;
+mod recursive;
+mod stateful;
+#[cfg(test)]
+mod test_util;
+
+pub use traits::*;
+pub use partial::{Partial, char, tag, tag_no_case, take_while, take_while1, take, take_until, take_till, length_data, length_value, one_of, none_of};
+pub use span::LocatedSpan;
+pub use stateful::Stateful;
+pub use branch::{Alt, alt, dispatch, Permutation, permutation};
+pub use sequence::{Tuple, tuple};
+pub use escape::{escaped, escaped_transform};
+pub use set::ByteSet;
+pub use recursive::{BoxedParser, Recursive, RecursiveRef, recursive};
 
 pub type IResult<I, O, E=(I,u32)> = Result<(I, O), Err<E>>;
 
+/// how much data a streaming parser needs before it can make progress
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Needed {
   Unknown,
+  /// number of additional bytes (or elements, for `&[T]`) required, on
+  /// top of the input that was given to the parser
   Size(usize),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Err<E> {
   Incomplete(Needed),
   Error(E),
@@ -38,7 +78,7 @@ impl<E1> Err<E1> {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ErrorKind {
   Alt,
   Many0,
@@ -48,16 +88,47 @@ pub enum ErrorKind {
   TakeWhile,
   TakeWhile1,
   ParseTo,
+  ManyMN,
+  Count,
+  ManyTill,
+  Permutation,
+  Eof,
+  Not,
+  Verify,
+  MapRes,
+  MapOpt,
+  Take,
+  TakeUntil,
+  LengthValue,
+  Escaped,
+  EscapedTransform,
+  OneOf,
+  NoneOf,
+  Dispatch,
 }
 
 pub trait Er<I> {
   fn from_error_kind(input: I, kind: ErrorKind) -> Self;
 
   fn or(self, other: Self) -> Self;
+
+  /// called by `context` when a parser fails, error types that do not
+  /// keep a trace can ignore it
+  fn add_context(self, _input: I, _context: &'static str) -> Self where Self: Sized {
+    self
+  }
+
+  /// called by `map_res_err` when the conversion fails. By default the
+  /// external error is dropped
+  fn from_external_error<X>(input: I, kind: ErrorKind, _e: X) -> Self
+    where X: std::error::Error + Send + Sync + 'static,
+          Self: Sized {
+    Self::from_error_kind(input, kind)
+  }
 }
 
 impl<I> Er<I> for (I, u32) {
-  fn from_error_kind(input: I, kind: ErrorKind) -> Self {
+  fn from_error_kind(input: I, _kind: ErrorKind) -> Self {
     (input, 0)
   }
 
@@ -65,6 +136,8 @@ impl<I> Er<I> for (I, u32) {
     other
   }
 }
+// the fields are only read through `Debug`
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct Simple<I> {
   i: I,
@@ -84,10 +157,12 @@ impl<I> Er<I> for Simple<I> {
   }
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 enum VerboseKind {
   E(ErrorKind),
   Context(&'static str),
+  External(ErrorKind, Box<dyn std::error::Error + Send + Sync>),
 }
 
 #[derive(Debug)]
@@ -105,8 +180,8 @@ impl<I> Verbose<I> {
   }
 }
 
-impl<'a> Er<&'a [u8]> for Verbose<&'a [u8]> {
-  fn from_error_kind(input: &'a [u8], kind: ErrorKind) -> Self {
+impl<I: InputLength> Er<I> for Verbose<I> {
+  fn from_error_kind(input: I, kind: ErrorKind) -> Self {
     Verbose {
       v: vec![(input, VerboseKind::E(kind))],
     }
@@ -114,171 +189,580 @@ impl<'a> Er<&'a [u8]> for Verbose<&'a [u8]> {
 
   fn or(self, other: Self) -> Self {
     //println!("or: self: {:?}, other: {:?}", self, other);
-    // take the error from the branch that went the farthest
-    let p1 = self.v.first().unwrap().0.as_ptr();
-    let p2 = other.v.first().unwrap().0.as_ptr();
-    //println!("p1: {:x?}, p2: {:x?}", p1, p2);
-    if p1 <= p2 {
+    // take the error from the branch that went the farthest, i.e. the
+    // one with the least input left
+    let l1 = self.v.first().unwrap().0.input_len();
+    let l2 = other.v.first().unwrap().0.input_len();
+    if l1 >= l2 {
       other
     } else {
       self
     }
   }
+
+  fn add_context(self, input: I, context: &'static str) -> Self {
+    self.append(input, context)
+  }
+
+  fn from_external_error<X>(input: I, kind: ErrorKind, e: X) -> Self
+    where X: std::error::Error + Send + Sync + 'static {
+    Verbose {
+      v: vec![(input, VerboseKind::External(kind, Box::new(e)))],
+    }
+  }
+}
+
+/// All the combinators are built on this trait. It is implemented for any
+/// `FnMut(I) -> IResult<I, O, E>`, so plain functions and closures can be
+/// used as parsers, and chained with the methods below
+pub trait Parser<I, O, E> {
+  fn parse(&mut self, input: I) -> IResult<I, O, E>;
+
+  /// applies a function to the result of the parser
+  fn map<G, O2>(self, g: G) -> Map<Self, G, O>
+    where G: FnMut(O) -> O2,
+          Self: Sized {
+    Map { f: self, g, phantom: PhantomData }
+  }
+
+  /// applies a second parser over the output of the first one
+  fn and_then<G, O2>(self, g: G) -> AndThen<Self, G, O>
+    where G: Parser<O, O2, E>,
+          Self: Sized {
+    AndThen { f: self, g, phantom: PhantomData }
+  }
+
+  /// tries the second parser if the first one returned an error
+  fn or<G>(self, g: G) -> Or<Self, G>
+    where G: Parser<I, O, E>,
+          Self: Sized {
+    Or { f: self, g }
+  }
+
+  /// repeats the parser until it fails, see `many0`
+  fn many0(self) -> Many0<Self>
+    where Self: Sized {
+    Many0 { f: self }
+  }
+
+  /// adds a context to the error, see `context`
+  fn context(self, s: &'static str) -> Context<Self>
+    where Self: Sized {
+    Context { f: self, s }
+  }
+
+  /// moves the parser to the heap, see `BoxedParser`
+  fn boxed<'a>(self) -> BoxedParser<'a, I, O, E>
+    where Self: Sized + 'a {
+    BoxedParser::new(self)
+  }
+}
+
+impl<I, O, E, F> Parser<I, O, E> for F
+  where F: FnMut(I) -> IResult<I, O, E> {
+  fn parse(&mut self, input: I) -> IResult<I, O, E> {
+    self(input)
+  }
+}
+
+pub struct Map<F, G, O1> {
+  f: F,
+  g: G,
+  phantom: PhantomData<O1>,
+}
+
+impl<I, O1, O2, E, F: Parser<I, O1, E>, G: FnMut(O1) -> O2> Parser<I, O2, E> for Map<F, G, O1> {
+  fn parse(&mut self, input: I) -> IResult<I, O2, E> {
+    match self.f.parse(input) {
+      Err(e) => Err(e),
+      Ok((i, o1)) => Ok((i, (self.g)(o1))),
+    }
+  }
+}
+
+pub struct AndThen<F, G, O1> {
+  f: F,
+  g: G,
+  phantom: PhantomData<O1>,
 }
 
-pub fn context<I: Clone, O, F>(mut parser: F, s: &'static str) -> impl FnMut(I) -> IResult<I, O, Verbose<I>>
-  where F: FnMut(I) -> IResult<I, O, Verbose<I>> {
+impl<I, O1, O2, E, F: Parser<I, O1, E>, G: Parser<O1, O2, E>> Parser<I, O2, E> for AndThen<F, G, O1> {
+  fn parse(&mut self, input: I) -> IResult<I, O2, E> {
+    let (i, o1) = self.f.parse(input)?;
+    let (_, o2) = self.g.parse(o1)?;
+    Ok((i, o2))
+  }
+}
+
+pub struct Or<F, G> {
+  f: F,
+  g: G,
+}
+
+impl<I: Clone, O, E: Er<I>, F: Parser<I, O, E>, G: Parser<I, O, E>> Parser<I, O, E> for Or<F, G> {
+  fn parse(&mut self, input: I) -> IResult<I, O, E> {
+    match self.f.parse(input.clone()) {
+      Err(Err::Error(e1)) => match self.g.parse(input) {
+        Err(Err::Error(e2)) => Err(Err::Error(e1.or(e2))),
+        res => res,
+      },
+      res => res,
+    }
+  }
+}
+
+pub struct Many0<F> {
+  f: F,
+}
+
+impl<I: Clone+InputLength+InputIsPartial, O, E: Er<I>, F: Parser<I, O, E>> Parser<I, Vec<O>, E> for Many0<F> {
+  fn parse(&mut self, input: I) -> IResult<I, Vec<O>, E> {
+    many0(|i| self.f.parse(i))(input)
+  }
+}
+
+pub struct Context<F> {
+  f: F,
+  s: &'static str,
+}
+
+impl<I: Clone, O, E: Er<I>, F: Parser<I, O, E>> Parser<I, O, E> for Context<F> {
+  fn parse(&mut self, input: I) -> IResult<I, O, E> {
+    let s = self.s;
+    context(|i| self.f.parse(i), s)(input)
+  }
+}
+
+pub fn context<I: Clone, O, E: Er<I>, F>(mut parser: F, s: &'static str) -> impl FnMut(I) -> IResult<I, O, E>
+  where F: Parser<I, O, E> {
 
   move |input: I| {
-    match parser(input.clone()) {
-      Ok(res) => return Ok(res),
+    match parser.parse(input.clone()) {
+      Ok(res) => Ok(res),
       Err(Err::Incomplete(i)) => Err(Err::Incomplete(i)),
-      Err(Err::Error(e)) => Err(Err::Error(e.append(input, s))),
-      Err(Err::Failure(e)) => Err(Err::Failure(e.append(input, s))),
+      Err(Err::Error(e)) => Err(Err::Error(e.add_context(input, s))),
+      Err(Err::Failure(e)) => Err(Err::Failure(e.add_context(input, s))),
     }
   }
 }
 
-/*************************/
+/// transforms an `Err::Error` into an `Err::Failure`
+///
+/// use it once a parser has seen enough input to know which branch it is
+/// in: `alt`, `or` and the repetition combinators stop at the first
+/// `Failure` instead of trying other branches, so the actual error is
+/// reported
+pub fn cut<I, O, E: Er<I>, F>(mut parser: F) -> impl FnMut(I) -> IResult<I, O, E>
+  where F: Parser<I, O, E> {
 
-//named!(first<u32>, flat_map!(digit, parse_to!(u32)));
-//named!(second<u32>, call!(be_u32));
+  move |input: I| {
+    match parser.parse(input) {
+      Err(Err::Error(e)) => Err(Err::Failure(e)),
+      res => res,
+    }
+  }
+}
 
-pub fn or<'b, I: Clone, O, E: Er<I>>(input: I, fns: &'b[&'b Fn(I) -> IResult<I, O, E>]) -> IResult<I, O, E> {
-  let mut index = 0;
+/*************************/
 
-  for f in fns.iter() {
-    match f(input.clone()) {
-      Err(Err::Error(_)) => {},
-      rest => return rest,
-    }
+pub fn preceded<I, O1, O2, E: Er<I>, F, G>(mut first: F, mut second: G) -> impl FnMut(I) -> IResult<I, O2, E>
+  where F: Parser<I, O1, E>,
+        G: Parser<I, O2, E> {
 
+  move |input: I| {
+    let (input, _) = first.parse(input)?;
+    second.parse(input)
   }
+}
+
+/// applies two parsers one after the other and returns both results
+pub fn pair<I, O1, O2, E: Er<I>, F, G>(mut first: F, mut second: G) -> impl FnMut(I) -> IResult<I, (O1, O2), E>
+  where F: Parser<I, O1, E>,
+        G: Parser<I, O2, E> {
 
-  Err(Err::Error(E::from_error_kind(input, ErrorKind::Alt)))
+  move |input: I| {
+    let (input, o1) = first.parse(input)?;
+    second.parse(input).map(|(i, o2)| (i, (o1, o2)))
+  }
 }
 
-pub fn preceded<I: Clone, O1, O2, E: Er<I>, F, G>(input: I, first: F, second: G) -> IResult<I, O2, E>
-  where F: Fn(I) -> IResult<I, O1, E>,
-        G: Fn(I) -> IResult<I, O2, E> {
+/// applies two parsers one after the other and returns the result of the
+/// first one
+pub fn terminated<I, O1, O2, E: Er<I>, F, G>(mut first: F, mut second: G) -> impl FnMut(I) -> IResult<I, O1, E>
+  where F: Parser<I, O1, E>,
+        G: Parser<I, O2, E> {
 
-  let (input, o1) = first(input)?;
-  second(input)
+  move |input: I| {
+    let (input, o1) = first.parse(input)?;
+    second.parse(input).map(|(i, _)| (i, o1))
+  }
 }
 
-pub fn separated<I: Clone, O1, O2, O3, E: Er<I>, F, G, H>(input: I, first: F, sep: G, second: H) -> IResult<I, (O1, O3), E>
-  where F: Fn(I) -> IResult<I, O1, E>,
-        G: Fn(I) -> IResult<I, O2, E>,
-        H: Fn(I) -> IResult<I, O3, E> {
+pub fn separated<I, O1, O2, O3, E: Er<I>, F, G, H>(mut first: F, mut sep: G, mut second: H) -> impl FnMut(I) -> IResult<I, (O1, O3), E>
+  where F: Parser<I, O1, E>,
+        G: Parser<I, O2, E>,
+        H: Parser<I, O3, E> {
 
-  let (input, o1) = first(input)?;
-  let (input, _)  = sep(input)?;
-  second(input).map(|(i, o2)| (i, (o1, o2)))
+  move |input: I| {
+    let (input, o1) = first.parse(input)?;
+    let (input, _)  = sep.parse(input)?;
+    second.parse(input).map(|(i, o2)| (i, (o1, o2)))
+  }
 }
 
-pub fn delimited<I: Clone, O1, O2, O3, E: Er<I>, F, G, H>(input: I, first: F, sep: G, second: H) -> IResult<I, O2, E>
-  where F: Fn(I) -> IResult<I, O1, E>,
-        G: Fn(I) -> IResult<I, O2, E>,
-        H: Fn(I) -> IResult<I, O3, E> {
+pub fn delimited<I, O1, O2, O3, E: Er<I>, F, G, H>(mut first: F, mut sep: G, mut second: H) -> impl FnMut(I) -> IResult<I, O2, E>
+  where F: Parser<I, O1, E>,
+        G: Parser<I, O2, E>,
+        H: Parser<I, O3, E> {
 
-  let (input, _) = first(input)?;
-  let (input, o2)  = sep(input)?;
-  second(input).map(|(i, _)| (i, o2))
+  move |input: I| {
+    let (input, _) = first.parse(input)?;
+    let (input, o2)  = sep.parse(input)?;
+    second.parse(input).map(|(i, _)| (i, o2))
+  }
 }
 
-pub fn take_while<'a, T: 'a, F, E: Er<&'a[T]>>(input: &'a [T], cond: F) -> IResult<&'a [T], &'a [T], E>
-  where F: Fn(T) -> bool,
-        &'a [T]: nom::InputTakeAtPosition<Item=T> {
-  input.split_at_position(|c| !cond(c)).map_err(|_| Err::Error(E::from_error_kind(input, ErrorKind::TakeWhile)))
+pub fn map<I, O1, O2, E: Er<I>, F, G>(mut first: F, mut second: G) -> impl FnMut(I) -> IResult<I, O2, E>
+  where F: Parser<I, O1, E>,
+        G: FnMut(O1) -> O2 {
+
+  move |input: I| {
+    first.parse(input).map(|(i, o1)| (i, second(o1)))
+  }
 }
 
-//#[inline(always)]
-pub fn take_while1<'a, T: 'a, F, E: Er<&'a[T]>>(input: &'a [T], cond: F) -> IResult<&'a [T], &'a [T], E>
-  where F: Fn(T) -> bool,
-        &'a [T]: nom::InputTakeAtPosition<Item=T> {
-  match input.split_at_position(|c| !cond(c)) {
-    Err(_) => Err(Err::Error(E::from_error_kind(input, ErrorKind::TakeWhile1))),
-    Ok(s) => if s.1.is_empty() {
-      Err(Err::Error(E::from_error_kind(input, ErrorKind::TakeWhile1)))
-    } else {
-      Ok(s)
-    }
+pub fn flat_map<I, O1, O2, E1: Er<I>+From<E2>, E2: Er<O1>, F, G>(mut first: F, mut second: G) -> impl FnMut(I) -> IResult<I, O2, E1>
+  where F: Parser<I, O1, E1>,
+        G: Parser<O1, O2, E2> {
+
+  move |input: I| {
+    let (i, o1) = first.parse(input)?;
+    second.parse(o1).map(|(_, o2)| (i, o2)).map_err(Err::convert)
   }
 }
 
-pub fn map<I, O1, O2, E: Er<I>, F, G>(input: I, first: F, second: G) -> IResult<I, O2, E>
-  where F: Fn(I) -> IResult<I, O1, E>,
-        G: Fn(O1) -> O2 {
+/// repeats the parser until it returns an error, and returns the results
+/// in a `Vec`. `Err::Failure` and `Err::Incomplete` from the parser are
+/// returned as is
+///
+/// on a partial input (see `Partial`), reaching the end of the data
+/// returns `Incomplete` instead of the elements parsed so far
+pub fn many0<I: Clone+InputLength+InputIsPartial, O, E: Er<I>, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
+  where F: Parser<I, O, E> {
+  many0_into(f, || Vec::with_capacity(4))
+}
 
-  first(input).map(|(i, o1)| (i, second(o1)))
+/// like `many0`, but fails if the parser does not match at least once
+pub fn many1<I: Clone+InputLength+InputIsPartial, O, E: Er<I>, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
+  where F: Parser<I, O, E> {
+  many1_into(f, || Vec::with_capacity(4))
 }
 
-pub fn flat_map<I: Clone+From<O1>, O1, O2, E1: Er<I>+From<E2>, E2: Er<O1>, F, G>(input: I, first: F, second: G) -> IResult<I, O2, E1>
-  where F: Fn(I) -> IResult<I, O1, E1>,
-        G: Fn(O1) -> IResult<O1, O2, E2> {
+/// like `many0`, but instead of building a `Vec`, folds the results into
+/// an accumulator created by `init` on each call:
+/// `fold_many0(f, || 0, |n, _| n + 1)`
+pub fn fold_many0<I: Clone+InputLength+InputIsPartial, O, R, E: Er<I>, F, G, H>(mut f: F, mut init: H, mut g: G) -> impl FnMut(I) -> IResult<I, R, E>
+  where F: Parser<I, O, E>,
+        G: FnMut(R, O) -> R,
+        H: FnMut() -> R {
+
+  move |input: I| {
+    let mut i = input;
+    let mut acc = init();
+
+    loop {
+      let i_ = i.clone();
+      match f.parse(i_) {
+        Err(Err::Error(_)) => return Ok((i, acc)),
+        Err(e) => return Err(e),
+        Ok((i2, o)) => {
+          if i.input_len() == i2.input_len() {
+            return Err(Err::Error(E::from_error_kind(i, ErrorKind::Many0)))
+          }
 
-  let (i, o1) = first(input)?;
-  second(o1).map(|(_, o2)| (i, o2)).map_err(Err::convert)
+          i = i2;
+          acc = g(acc, o);
+
+          if i.input_len() == 0 {
+            if i.is_partial() {
+              return Err(Err::Incomplete(Needed::Unknown));
+            }
+            return Ok((i, acc));
+          }
+        }
+      }
+    }
+  }
 }
 
-pub fn many0<I: Clone+InputLength, O, E: Er<I>, F>(input: I, mut f: F) -> IResult<I, Vec<O>, E>
-  where F: FnMut(I) -> IResult<I, O, E> {
+/// like `many1`, but instead of building a `Vec`, folds the results into
+/// an accumulator created by `init`, see `fold_many0`
+pub fn fold_many1<I: Clone+InputLength+InputIsPartial, O, R, E: Er<I>, F, G, H>(mut f: F, mut init: H, mut g: G) -> impl FnMut(I) -> IResult<I, R, E>
+  where F: Parser<I, O, E>,
+        G: FnMut(R, O) -> R,
+        H: FnMut() -> R {
 
-  let mut i = input;
-  let mut acc = Vec::with_capacity(4);
+  move |input: I| {
+    let i = input;
 
-  loop {
     let i_ = i.clone();
-    match f(i_) {
-      Err(_) => return Ok((i, acc)),
+    match f.parse(i_) {
+      Err(Err::Error(_)) => {
+        Err(Err::Error(E::from_error_kind(i, ErrorKind::Many1)))
+      },
+      Err(e) => Err(e),
       Ok((i2, o)) => {
-        if i.input_len() == i2.input_len() {
-          return Err(Err::Error(E::from_error_kind(i, ErrorKind::Many0)))
+        let mut acc = g(init(), o);
+        let mut i = i2;
+
+        loop {
+          if i.input_len() == 0 {
+            if i.is_partial() {
+              return Err(Err::Incomplete(Needed::Unknown));
+            }
+            return Ok((i, acc));
+          }
+
+          let i_ = i.clone();
+          match f.parse(i_) {
+            Err(Err::Error(_)) => {
+              return Ok((i, acc));
+            },
+            Err(e) => return Err(e),
+            Ok((i2, o)) => {
+              if i.input_len() == i2.input_len() {
+                return Err(Err::Error(E::from_error_kind(i, ErrorKind::Many1)))
+              }
+
+              i = i2;
+              acc = g(acc, o);
+            }
+          }
         }
+      }
+    }
+  }
+}
 
-        i = i2;
-        acc.push(o);
+/// like `many0`, but collects the results in any container implementing
+/// `Extend`, created by `init` on each call: `many0_into(f, HashMap::new)`
+///
+/// to append to a `Vec` reused across parses, fold into it instead, and
+/// build the parser for each parse so it only borrows the `Vec` during
+/// that call: `fold_many0(f, || (), |(), o| buf.push(o))(input)`
+pub fn many0_into<I: Clone+InputLength+InputIsPartial, O, C: Extend<O>, E: Er<I>, F, H>(f: F, init: H) -> impl FnMut(I) -> IResult<I, C, E>
+  where F: Parser<I, O, E>,
+        H: FnMut() -> C {
+  fold_many0(f, init, extend)
+}
 
-        if i.input_len() == 0 {
-          return Ok((i, acc));
+/// like `many1`, but collects the results in any container implementing
+/// `Extend`, see `many0_into`
+pub fn many1_into<I: Clone+InputLength+InputIsPartial, O, C: Extend<O>, E: Er<I>, F, H>(f: F, init: H) -> impl FnMut(I) -> IResult<I, C, E>
+  where F: Parser<I, O, E>,
+        H: FnMut() -> C {
+  fold_many1(f, init, extend)
+}
+
+#[inline]
+fn extend<O, C: Extend<O>>(mut acc: C, o: O) -> C {
+  acc.extend(Some(o));
+  acc
+}
+
+// the counts given to `many_m_n` and `count` often come from the input, so
+// they only reserve up to 64kB up front
+fn initial_capacity<O>(n: usize) -> usize {
+  const MAX_INITIAL_CAPACITY_BYTES: usize = 65536;
+
+  n.min(MAX_INITIAL_CAPACITY_BYTES / std::mem::size_of::<O>().max(1))
+}
+
+/// applies the parser between `m` and `n` times (inclusive), and returns
+/// the results in a `Vec`
+///
+/// returns an error if the parser matched less than `m` times
+pub fn many_m_n<I: Clone+InputLength+InputIsPartial, O, E: Er<I>, F>(m: usize, n: usize, mut f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
+  where F: Parser<I, O, E> {
+
+  move |input: I| {
+    let mut i = input;
+    let mut acc = Vec::with_capacity(initial_capacity::<O>(m));
+
+    while acc.len() < n {
+      let i_ = i.clone();
+      match f.parse(i_) {
+        Err(Err::Error(_)) => break,
+        Err(e) => return Err(e),
+        Ok((i2, o)) => {
+          if i.input_len() == i2.input_len() {
+            return Err(Err::Error(E::from_error_kind(i, ErrorKind::ManyMN)))
+          }
+
+          i = i2;
+          acc.push(o);
+
+          if i.input_len() == 0 && acc.len() < n {
+            if i.is_partial() {
+              return Err(Err::Incomplete(Needed::Unknown));
+            }
+            break;
+          }
         }
       }
     }
+
+    if acc.len() < m {
+      Err(Err::Error(E::from_error_kind(i, ErrorKind::ManyMN)))
+    } else {
+      Ok((i, acc))
+    }
   }
 }
 
-pub fn many1<I: Clone+Copy+InputLength, O, E: Er<I>, F>(input: I, f: F) -> IResult<I, Vec<O>, E>
-  where F: Fn(I) -> IResult<I, O, E> {
-  //many1!(input, f)
+/// applies the parser exactly `count` times
+pub fn count<I: Clone, O, E: Er<I>, F>(mut f: F, count: usize) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
+  where F: Parser<I, O, E> {
+
+  move |input: I| {
+    let mut i = input;
+    let mut acc = Vec::with_capacity(initial_capacity::<O>(count));
+
+    for _ in 0..count {
+      let i_ = i.clone();
+      match f.parse(i_) {
+        Err(Err::Error(_)) => {
+          return Err(Err::Error(E::from_error_kind(i, ErrorKind::Count)))
+        },
+        Err(e) => return Err(e),
+        Ok((i2, o)) => {
+          i = i2;
+          acc.push(o);
+        }
+      }
+    }
 
-  let mut i = input;
+    Ok((i, acc))
+  }
+}
 
-  let i_ = i.clone();
-  match f(i_) {
-    Err(_) => {
-      return Err(Err::Error(E::from_error_kind(i, ErrorKind::Many1)))
-    },
-    Ok((i2, o)) => {
-      let mut acc = Vec::with_capacity(4);
-      acc.push(o);
-      let mut i = i2;
+/// applies `f` until `g` succeeds, and returns the results of `f` along
+/// with the result of `g`
+///
+/// returns an error if `f` fails before `g` matched
+pub fn many_till<I: Clone+InputLength, O1, O2, E: Er<I>, F, G>(mut f: F, mut g: G) -> impl FnMut(I) -> IResult<I, (Vec<O1>, O2), E>
+  where F: Parser<I, O1, E>,
+        G: Parser<I, O2, E> {
 
-      loop {
-        let i_ = i.clone();
-        match f(i_) {
-          Err(_) => {
-            return Ok((i, acc));
-          },
-          Ok((i2, o)) => {
-            if i.input_len() == i2.input_len() {
-              return Err(Err::Error(E::from_error_kind(i, ErrorKind::Many1)))
+  move |input: I| {
+    let mut i = input;
+    let mut acc = Vec::with_capacity(4);
+
+    loop {
+      let i_ = i.clone();
+      match g.parse(i_) {
+        Ok((i2, o2)) => return Ok((i2, (acc, o2))),
+        Err(Err::Error(_)) => {
+          let i_ = i.clone();
+          match f.parse(i_) {
+            Err(Err::Error(_)) => {
+              return Err(Err::Error(E::from_error_kind(i, ErrorKind::ManyTill)))
+            },
+            Err(e) => return Err(e),
+            Ok((i2, o)) => {
+              if i.input_len() == i2.input_len() {
+                return Err(Err::Error(E::from_error_kind(i, ErrorKind::ManyTill)))
+              }
+
+              i = i2;
+              acc.push(o);
             }
+          }
+        },
+        Err(e) => return Err(e),
+      }
+    }
+  }
+}
 
-            i = i2;
-            acc.push(o);
+/// parses a possibly empty list of elements separated by `sep`. `Err::Failure`
+/// and `Err::Incomplete` from either parser are returned as is
+///
+/// on a partial input, reaching the end of the data returns `Incomplete`
+pub fn separated_list0<I: Clone+InputLength+InputIsPartial, O, O2, E: Er<I>, F, G>(sep: G, f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
+  where F: Parser<I, O, E>,
+        G: Parser<I, O2, E> {
+  separated_list0_into(sep, f, Vec::new)
+}
 
-            if i.input_len() == 0 {
-              return Ok((i, acc));
+/// parses a non empty list of elements separated by `sep`. Returns the
+/// error of `f` if the first element does not match. `Err::Failure` and
+/// `Err::Incomplete` from either parser are returned as is
+///
+/// on a partial input, reaching the end of the data returns `Incomplete`
+pub fn separated_list1<I: Clone+InputLength+InputIsPartial, O, O2, E: Er<I>, F, G>(sep: G, f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
+  where F: Parser<I, O, E>,
+        G: Parser<I, O2, E> {
+  separated_list1_into(sep, f, Vec::new)
+}
+
+/// like `separated_list0`, but accepts and consumes one separator after
+/// the last element, as in `[1, 2, 3,]`
+///
+/// on a partial input, reaching the end of the data returns `Incomplete`
+pub fn separated_list0_trailing<I: Clone+InputLength+InputIsPartial, O, O2, E: Er<I>, F, G>(mut sep: G, mut f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
+  where F: Parser<I, O, E>,
+        G: Parser<I, O2, E> {
+
+  move |input: I| {
+    let mut acc = Vec::new();
+    let i_ = input.clone();
+    let mut i = match f.parse(i_) {
+      Err(Err::Error(_)) => return Ok((input, acc)),
+      Err(e) => return Err(e),
+      Ok((i, o)) => {
+        acc.push(o);
+        i
+      }
+    };
+
+    loop {
+      if i.input_len() == 0 {
+        if i.is_partial() {
+          return Err(Err::Incomplete(Needed::Unknown));
+        }
+        return Ok((i, acc));
+      }
+
+      let i_ = i.clone();
+      match sep.parse(i_) {
+        Err(Err::Error(_)) => return Ok((i, acc)),
+        Err(e) => return Err(e),
+        Ok((i2, _)) => {
+          if i.input_len() == i2.input_len() {
+            return Err(Err::Error(E::from_error_kind(i, ErrorKind::Many0)))
+          }
+
+          // the separator was consumed, a missing element is now accepted
+          i = i2;
+          if i.input_len() == 0 {
+            if i.is_partial() {
+              return Err(Err::Incomplete(Needed::Unknown));
+            }
+            return Ok((i, acc));
+          }
+
+          let i_ = i.clone();
+          match f.parse(i_) {
+            Err(Err::Error(_)) => return Ok((i, acc)),
+            Err(e) => return Err(e),
+            Ok((i3, o)) => {
+              if i.input_len() == i3.input_len() {
+                return Err(Err::Error(E::from_error_kind(i, ErrorKind::Many0)))
+              }
+
+              i = i3;
+              acc.push(o);
             }
           }
         }
@@ -287,38 +771,56 @@ pub fn many1<I: Clone+Copy+InputLength, O, E: Er<I>, F>(input: I, f: F) -> IResu
   }
 }
 
-pub fn separated_list<I: Clone+InputLength, O, O2, E: Er<I>, F, G>(input: I, mut sep: G, mut f: F) -> IResult<I, Vec<O>, E>
-  where F: FnMut(I) -> IResult<I, O, E>,
-        G: FnMut(I) -> IResult<I, O2, E> {
-  let mut acc = Vec::new();
-  let (input, o) = f(input)?;
-  acc.push(o);
+/// like `separated_list1`, but instead of building a `Vec`, folds the
+/// results into an accumulator created by `init`, see `fold_many0`
+pub fn fold_separated_list<I: Clone+InputLength+InputIsPartial, O, O2, R, E: Er<I>, F, G, H, K>(mut sep: G, mut f: F, mut init: K, mut h: H) -> impl FnMut(I) -> IResult<I, R, E>
+  where F: Parser<I, O, E>,
+        G: Parser<I, O2, E>,
+        H: FnMut(R, O) -> R,
+        K: FnMut() -> R {
+
+  move |input: I| {
+    let (i, o) = f.parse(input)?;
+    let acc = h(init(), o);
+    fold_separated_tail(i, &mut sep, &mut f, acc, &mut h)
+  }
+}
 
-  let mut i = input;
+// folds the `sep f` pairs following the first element of a separated list
+#[inline]
+fn fold_separated_tail<I: Clone+InputLength+InputIsPartial, O, O2, R, E: Er<I>, F, G, H>(mut i: I, sep: &mut G, f: &mut F, mut acc: R, h: &mut H) -> IResult<I, R, E>
+  where F: Parser<I, O, E>,
+        G: Parser<I, O2, E>,
+        H: FnMut(R, O) -> R {
 
   loop {
     if i.input_len() == 0 {
+      if i.is_partial() {
+        return Err(Err::Incomplete(Needed::Unknown));
+      }
       return Ok((i, acc));
     }
 
     let i_ = i.clone();
-    match sep(i_) {
-      Err(_) => return Ok((i, acc)),
+    match sep.parse(i_) {
+      Err(Err::Error(_)) => return Ok((i, acc)),
+      Err(e) => return Err(e),
       Ok((i2, _)) => {
         if i.input_len() == i2.input_len() {
           return Err(Err::Error(E::from_error_kind(i, ErrorKind::Many0)))
         }
 
         let i2_ = i2.clone();
-        match f(i2_) {
-          Err(_) => return Ok((i, acc)),
+        match f.parse(i2_) {
+          Err(Err::Error(_)) => return Ok((i, acc)),
+          Err(e) => return Err(e),
           Ok((i3, o)) => {
             if i2.input_len() == i3.input_len() {
               return Err(Err::Error(E::from_error_kind(i, ErrorKind::Many0)))
             }
 
             i = i3;
-            acc.push(o);
+            acc = h(acc, o);
           }
         }
       }
@@ -326,42 +828,210 @@ pub fn separated_list<I: Clone+InputLength, O, O2, E: Er<I>, F, G>(input: I, mut
   }
 }
 
-//#[inline(always)]
-pub fn char<'a, E: Er<&'a[u8]>>(c: char) -> impl Fn(&'a[u8]) -> IResult<&'a[u8], char, E> {
+/// like `separated_list0`, but collects the results in any container
+/// implementing `Extend`, see `many0_into`
+pub fn separated_list0_into<I: Clone+InputLength+InputIsPartial, O, O2, C: Extend<O>, E: Er<I>, F, G, H>(mut sep: G, mut f: F, mut init: H) -> impl FnMut(I) -> IResult<I, C, E>
+  where F: Parser<I, O, E>,
+        G: Parser<I, O2, E>,
+        H: FnMut() -> C {
 
-  move |i:&[u8]| {
-    if i.len() == 0 {
-      Err(Err::Incomplete(Needed::Unknown))
-    } else {
-      //beware of utf8
-      if i[0] as char == c {
-        Ok((&i[1..], c))
-      } else {
-        Err(Err::Error(E::from_error_kind(i, ErrorKind::Char)))
+  move |input: I| {
+    let i_ = input.clone();
+    match f.parse(i_) {
+      Err(Err::Error(_)) => Ok((input, init())),
+      Err(e) => Err(e),
+      Ok((i, o)) => {
+        let acc = extend(init(), o);
+        fold_separated_tail(i, &mut sep, &mut f, acc, &mut extend)
       }
     }
   }
 }
 
-pub fn tag<'b, 'a: 'b, E: Er<&'b[u8]>>(t: &'a [u8]) -> impl Fn(&'b [u8]) -> IResult<&'b [u8], &'b [u8], E> {
-  move |i:&'b [u8]| {
-    let tag_len = t.input_len();
-    let res: IResult<_, _, E> = match i.compare(t) {
-      CompareResult::Ok => Ok(i.take_split(tag_len)),
-      //CompareResult::Incomplete => need_more(i, Needed::Size(tag_len)),
-      CompareResult::Incomplete | CompareResult::Error => {
-        Err(Err::Error(E::from_error_kind(i, ErrorKind::Tag)))
-      }
-    };
+/// like `separated_list1`, but collects the results in any container
+/// implementing `Extend`, see `many0_into`
+pub fn separated_list1_into<I: Clone+InputLength+InputIsPartial, O, O2, C: Extend<O>, E: Er<I>, F, G, H>(sep: G, f: F, init: H) -> impl FnMut(I) -> IResult<I, C, E>
+  where F: Parser<I, O, E>,
+        G: Parser<I, O2, E>,
+        H: FnMut() -> C {
+  fold_separated_list(sep, f, init, extend)
+}
+
+pub fn value<I, O1, O2: Clone, E: Er<I>, F>(mut f: F, o: O2) -> impl FnMut(I) -> IResult<I, O2, E>
+  where F: Parser<I, O1, E> {
+
+  move |input: I| {
+    f.parse(input).map(|(i, _)| (i, o.clone()))
+  }
+}
+
+/// makes a parser optional: returns `None` instead of `Err::Error`
+pub fn opt<I: Clone, O, E: Er<I>, F>(mut f: F) -> impl FnMut(I) -> IResult<I, Option<O>, E>
+  where F: Parser<I, O, E> {
+
+  move |input: I| {
+    match f.parse(input.clone()) {
+      Ok((i, o)) => Ok((i, Some(o))),
+      Err(Err::Error(_)) => Ok((input, None)),
+      Err(e) => Err(e),
+    }
+  }
+}
+
+/// applies the parser only if `b` is true, returns `None` otherwise
+pub fn cond<I, O, E: Er<I>, F>(b: bool, mut f: F) -> impl FnMut(I) -> IResult<I, Option<O>, E>
+  where F: Parser<I, O, E> {
+
+  move |input: I| {
+    if b {
+      f.parse(input).map(|(i, o)| (i, Some(o)))
+    } else {
+      Ok((input, None))
+    }
+  }
+}
+
+/// applies the parser without consuming the input
+pub fn peek<I: Clone, O, E: Er<I>, F>(mut f: F) -> impl FnMut(I) -> IResult<I, O, E>
+  where F: Parser<I, O, E> {
+
+  move |input: I| {
+    let (_, o) = f.parse(input.clone())?;
+    Ok((input, o))
+  }
+}
+
+/// succeeds without consuming anything if the parser returns an error,
+/// and returns an error if it succeeds
+pub fn not<I: Clone, O, E: Er<I>, F>(mut f: F) -> impl FnMut(I) -> IResult<I, (), E>
+  where F: Parser<I, O, E> {
 
-    res
+  move |input: I| {
+    match f.parse(input.clone()) {
+      Ok(_) => Err(Err::Error(E::from_error_kind(input, ErrorKind::Not))),
+      Err(Err::Error(_)) => Ok((input, ())),
+      Err(e) => Err(e),
+    }
+  }
+}
+
+/// succeeds on empty input, returns an error otherwise
+///
+/// on a partial input, more data could still arrive, so empty input
+/// returns `Incomplete`
+pub fn eof<I: Clone+InputLength+InputIsPartial, E: Er<I>>(input: I) -> IResult<I, I, E> {
+  if input.input_len() != 0 {
+    Err(Err::Error(E::from_error_kind(input, ErrorKind::Eof)))
+  } else if input.is_partial() {
+    Err(Err::Incomplete(Needed::Unknown))
+  } else {
+    Ok((input.clone(), input))
   }
 }
 
-pub fn value<I, O1, O2, E: Er<I>, F>(input: I, f: F, o: O2) -> IResult<I, O2, E>
-  where F: Fn(I) -> IResult<I, O1, E> {
+/// applies the parser and checks that it consumed the whole input, see
+/// `eof`
+pub fn all_consuming<I: Clone+InputLength+InputIsPartial, O, E: Er<I>, F>(mut f: F) -> impl FnMut(I) -> IResult<I, O, E>
+  where F: Parser<I, O, E> {
 
-  f(input).map(|(i, _)| (i, o))
+  move |input: I| {
+    let (i, o) = f.parse(input)?;
+    let (i, _) = eof(i)?;
+    Ok((i, o))
+  }
+}
+
+/// returns the remaining input. On a partial input, this is only the data
+/// available so far
+pub fn rest<I: InputTake+InputLength, E: Er<I>>(input: I) -> IResult<I, I, E> {
+  Ok(input.take_split(input.input_len()))
+}
+
+/// returns the result of the parser if `v` accepts it, an error otherwise
+pub fn verify<I: Clone, O, E: Er<I>, F, G>(mut f: F, mut v: G) -> impl FnMut(I) -> IResult<I, O, E>
+  where F: Parser<I, O, E>,
+        G: FnMut(&O) -> bool {
+
+  move |input: I| {
+    let (i, o) = f.parse(input.clone())?;
+    if v(&o) {
+      Ok((i, o))
+    } else {
+      Err(Err::Error(E::from_error_kind(input, ErrorKind::Verify)))
+    }
+  }
+}
+
+/// applies a fallible conversion to the result of the parser, like
+/// `map_res(digits, |s: &str| s.parse::<u32>())`
+///
+/// the conversion error is dropped, see `map_res_err` to keep it
+pub fn map_res<I: Clone, O1, O2, X, E: Er<I>, F, G>(mut f: F, mut g: G) -> impl FnMut(I) -> IResult<I, O2, E>
+  where F: Parser<I, O1, E>,
+        G: FnMut(O1) -> Result<O2, X> {
+
+  move |input: I| {
+    let (i, o1) = f.parse(input.clone())?;
+    match g(o1) {
+      Ok(o2) => Ok((i, o2)),
+      Err(_) => Err(Err::Error(E::from_error_kind(input, ErrorKind::MapRes))),
+    }
+  }
+}
+
+/// like `map_res`, but the conversion error is passed to
+/// `Er::from_external_error`, so an error type like `Verbose` can keep it
+pub fn map_res_err<I: Clone, O1, O2, X, E: Er<I>, F, G>(mut f: F, mut g: G) -> impl FnMut(I) -> IResult<I, O2, E>
+  where F: Parser<I, O1, E>,
+        G: FnMut(O1) -> Result<O2, X>,
+        X: std::error::Error + Send + Sync + 'static {
+
+  move |input: I| {
+    let (i, o1) = f.parse(input.clone())?;
+    match g(o1) {
+      Ok(o2) => Ok((i, o2)),
+      Err(e) => Err(Err::Error(E::from_external_error(input, ErrorKind::MapRes, e))),
+    }
+  }
+}
+
+/// applies a conversion returning an `Option` to the result of the parser,
+/// `None` is an error
+pub fn map_opt<I: Clone, O1, O2, E: Er<I>, F, G>(mut f: F, mut g: G) -> impl FnMut(I) -> IResult<I, O2, E>
+  where F: Parser<I, O1, E>,
+        G: FnMut(O1) -> Option<O2> {
+
+  move |input: I| {
+    let (i, o1) = f.parse(input.clone())?;
+    match g(o1) {
+      Some(o2) => Ok((i, o2)),
+      None => Err(Err::Error(E::from_error_kind(input, ErrorKind::MapOpt))),
+    }
+  }
+}
+
+/// returns the part of the input consumed by the parser, instead of
+/// its result
+pub fn recognize<I: Clone+Offset+Slice<RangeTo<usize>>, O, E: Er<I>, F>(mut f: F) -> impl FnMut(I) -> IResult<I, I, E>
+  where F: Parser<I, O, E> {
+
+  move |input: I| {
+    let (i, _) = f.parse(input.clone())?;
+    let index = input.offset(&i);
+    Ok((i, input.slice(..index)))
+  }
+}
+
+/// returns the part of the input consumed by the parser, along with its
+/// result
+pub fn consumed<I: Clone+Offset+Slice<RangeTo<usize>>, O, E: Er<I>, F>(mut f: F) -> impl FnMut(I) -> IResult<I, (I, O), E>
+  where F: Parser<I, O, E> {
+
+  move |input: I| {
+    let (i, o) = f.parse(input.clone())?;
+    let index = input.offset(&i);
+    Ok((i, (input.slice(..index), o)))
+  }
 }
 
 /****************************/
@@ -389,3 +1059,434 @@ fn manytest() {
   panic!();
 }
 */
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_util::{R, trace};
+
+  type P<'a> = Partial<&'a [u8]>;
+
+  fn p(s: &[u8]) -> P<'_> {
+    Partial::new(s)
+  }
+
+  // an `a` commits to the element, so `ac` is a `Failure`
+  fn elem(i: &[u8]) -> IResult<&[u8], char> {
+    preceded(char('a'), cut(char('b')))(i)
+  }
+
+  fn s_elem(i: &[u8]) -> IResult<&[u8], char> {
+    preceded(streaming::char('a'), cut(streaming::char('b')))(i)
+  }
+
+  fn p_elem(i: P) -> IResult<P, char> {
+    preceded(char('a'), cut(char('b')))(i)
+  }
+
+  #[test]
+  fn many0_outcomes() {
+    assert_eq!(many0(elem)(&b"ababx"[..]), Ok((&b"x"[..], vec!['b', 'b'])));
+    assert_eq!(many0(elem)(&b"abab"[..]), Ok((&b""[..], vec!['b', 'b'])));
+    assert_eq!(many0(elem)(&b"x"[..]), Ok((&b"x"[..], vec![])));
+    assert_eq!(many0(elem)(&b"abac"[..]), Err(Err::Failure((&b"c"[..], 0))));
+    assert_eq!(many0(s_elem)(&b"aba"[..]), Err(Err::Incomplete(Needed::Size(1))));
+
+    assert_eq!(many0(p_elem)(p(b"ababx")), Ok((p(b"x"), vec!['b', 'b'])));
+    assert_eq!(many0(p_elem)(p(b"abac")), Err(Err::Failure((p(b"c"), 0))));
+    assert_eq!(many0(p_elem)(p(b"aba")), Err(Err::Incomplete(Needed::Size(1))));
+    assert_eq!(many0(p_elem)(p(b"abab")), Err(Err::Incomplete(Needed::Unknown)));
+    assert_eq!(many0(p_elem)(p(b"abab").into_complete()), Ok((p(b"").into_complete(), vec!['b', 'b'])));
+  }
+
+  #[test]
+  fn many1_outcomes() {
+    assert_eq!(many1(elem)(&b"ababx"[..]), Ok((&b"x"[..], vec!['b', 'b'])));
+    assert_eq!(many1(elem)(&b"x"[..]), Err(Err::Error((&b"x"[..], 0))));
+    assert_eq!(many1(elem)(&b"ac"[..]), Err(Err::Failure((&b"c"[..], 0))));
+    assert_eq!(many1(elem)(&b"abac"[..]), Err(Err::Failure((&b"c"[..], 0))));
+    assert_eq!(many1(s_elem)(&b"a"[..]), Err(Err::Incomplete(Needed::Size(1))));
+    assert_eq!(many1(s_elem)(&b"aba"[..]), Err(Err::Incomplete(Needed::Size(1))));
+
+    assert_eq!(many1(p_elem)(p(b"ababx")), Ok((p(b"x"), vec!['b', 'b'])));
+    assert_eq!(many1(p_elem)(p(b"x")), Err(Err::Error((p(b"x"), 0))));
+    assert_eq!(many1(p_elem)(p(b"abac")), Err(Err::Failure((p(b"c"), 0))));
+    assert_eq!(many1(p_elem)(p(b"")), Err(Err::Incomplete(Needed::Size(1))));
+    assert_eq!(many1(p_elem)(p(b"aba")), Err(Err::Incomplete(Needed::Size(1))));
+    assert_eq!(many1(p_elem)(p(b"abab")), Err(Err::Incomplete(Needed::Unknown)));
+  }
+
+  #[test]
+  fn separated_list0_outcomes() {
+    let r = separated_list0(char(','), elem)(&b"ab,abx"[..]);
+    assert_eq!(r, Ok((&b"x"[..], vec!['b', 'b'])));
+    let r = separated_list0(char(','), elem)(&b"ab,x"[..]);
+    assert_eq!(r, Ok((&b",x"[..], vec!['b'])));
+    let r = separated_list0(char(','), elem)(&b"x"[..]);
+    assert_eq!(r, Ok((&b"x"[..], vec![])));
+    let r = separated_list0(char(','), elem)(&b"ab,ac"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"c"[..], 0))));
+    let r = separated_list0(streaming::char(','), s_elem)(&b"ab,a"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+
+    let r = separated_list0(char(','), p_elem)(p(b"ab,abx"));
+    assert_eq!(r, Ok((p(b"x"), vec!['b', 'b'])));
+    let r = separated_list0(char(','), p_elem)(p(b"ab,ac"));
+    assert_eq!(r, Err(Err::Failure((p(b"c"), 0))));
+    let r = separated_list0(char(','), p_elem)(p(b"ab,a"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r = separated_list0(char(','), p_elem)(p(b"ab,"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r = separated_list0(char(','), p_elem)(p(b"ab"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Unknown)));
+  }
+
+  #[test]
+  fn separated_list1_outcomes() {
+    let r = separated_list1(char(','), elem)(&b"ab,abx"[..]);
+    assert_eq!(r, Ok((&b"x"[..], vec!['b', 'b'])));
+    let r = separated_list1(char(','), elem)(&b"x"[..]);
+    assert_eq!(r, Err(Err::Error((&b"x"[..], 0))));
+    let r = separated_list1(char(','), elem)(&b"ab,ac"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"c"[..], 0))));
+    let r = separated_list1(streaming::char(','), s_elem)(&b"ab,a"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+
+    let r = separated_list1(char(','), p_elem)(p(b"ab,abx"));
+    assert_eq!(r, Ok((p(b"x"), vec!['b', 'b'])));
+    let r = separated_list1(char(','), p_elem)(p(b"x"));
+    assert_eq!(r, Err(Err::Error((p(b"x"), 0))));
+    let r = separated_list1(char(','), p_elem)(p(b"ab,ac"));
+    assert_eq!(r, Err(Err::Failure((p(b"c"), 0))));
+    let r = separated_list1(char(','), p_elem)(p(b"ab,a"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r = separated_list1(char(','), p_elem)(p(b"ab"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Unknown)));
+  }
+
+  #[test]
+  fn separated_list0_trailing_outcomes() {
+    let r = separated_list0_trailing(char(','), elem)(&b"ab,ab,x"[..]);
+    assert_eq!(r, Ok((&b"x"[..], vec!['b', 'b'])));
+    let r = separated_list0_trailing(char(','), elem)(&b"ab,"[..]);
+    assert_eq!(r, Ok((&b""[..], vec!['b'])));
+    let r = separated_list0_trailing(char(','), elem)(&b"ab,ac"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"c"[..], 0))));
+    let r = separated_list0_trailing(streaming::char(','), s_elem)(&b"ab,a"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+
+    let r = separated_list0_trailing(char(','), p_elem)(p(b"ab,x"));
+    assert_eq!(r, Ok((p(b"x"), vec!['b'])));
+    let r = separated_list0_trailing(char(','), p_elem)(p(b"ab,ac"));
+    assert_eq!(r, Err(Err::Failure((p(b"c"), 0))));
+    let r = separated_list0_trailing(char(','), p_elem)(p(b"ab,a"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r = separated_list0_trailing(char(','), p_elem)(p(b"ab,"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Unknown)));
+  }
+
+  #[test]
+  fn huge_count_does_not_allocate_up_front() {
+    assert_eq!(count(elem, usize::MAX)(&b"abx"[..]), Err(Err::Error((&b"x"[..], 0))));
+    assert_eq!(many_m_n(usize::MAX, usize::MAX, elem)(&b"abx"[..]), Err(Err::Error((&b"x"[..], 0))));
+  }
+
+  #[test]
+  fn fold_outcomes() {
+    let count = |n: usize, _| n + 1;
+
+    assert_eq!(fold_many0(elem, || 0, count)(&b"ababx"[..]), Ok((&b"x"[..], 2)));
+    assert_eq!(fold_many0(elem, || 0, count)(&b"abac"[..]), Err(Err::Failure((&b"c"[..], 0))));
+    assert_eq!(fold_many0(p_elem, || 0, count)(p(b"abab")), Err(Err::Incomplete(Needed::Unknown)));
+
+    assert_eq!(fold_many1(elem, || 0, count)(&b"x"[..]), Err(Err::Error((&b"x"[..], 0))));
+    assert_eq!(fold_many1(elem, || 0, count)(&b"abab"[..]), Ok((&b""[..], 2)));
+    assert_eq!(fold_many1(p_elem, || 0, count)(p(b"aba")), Err(Err::Incomplete(Needed::Size(1))));
+
+    let r = fold_separated_list(char(','), elem, || 0, count)(&b"ab,abx"[..]);
+    assert_eq!(r, Ok((&b"x"[..], 2)));
+    let r = fold_separated_list(char(','), elem, || 0, count)(&b"ab,ac"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"c"[..], 0))));
+    let r = fold_separated_list(char(','), p_elem, || 0, count)(p(b"ab,a"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+  }
+
+  #[test]
+  fn into_outcomes() {
+    use std::collections::BTreeSet;
+
+    assert_eq!(many0_into(elem, BTreeSet::new)(&b"ababx"[..]), Ok((&b"x"[..], Some('b').into_iter().collect())));
+    assert_eq!(many0_into(elem, BTreeSet::new)(&b"abac"[..]), Err(Err::Failure((&b"c"[..], 0))));
+    assert_eq!(many1_into(elem, BTreeSet::new)(&b"x"[..]), Err(Err::Error((&b"x"[..], 0))));
+    assert_eq!(many1_into(p_elem, BTreeSet::new)(p(b"aba")), Err(Err::Incomplete(Needed::Size(1))));
+
+    let r = separated_list0_into(char(','), elem, String::new)(&b"x"[..]);
+    assert_eq!(r, Ok((&b"x"[..], String::new())));
+    let r = separated_list0_into(char(','), elem, String::new)(&b"ab,abx"[..]);
+    assert_eq!(r, Ok((&b"x"[..], "bb".to_string())));
+    let r = separated_list0_into(char(','), p_elem, String::new)(p(b"ab,ac"));
+    assert_eq!(r, Err(Err::Failure((p(b"c"), 0))));
+    let r = separated_list1_into(char(','), elem, String::new)(&b"x"[..]);
+    assert_eq!(r, Err(Err::Error((&b"x"[..], 0))));
+    let r = separated_list1_into(char(','), p_elem, String::new)(p(b"ab,a"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+  }
+
+  #[test]
+  fn fold_into_reused_vec() {
+    let mut buf = Vec::new();
+
+    let r = fold_many0(elem, || (), |(), o| buf.push(o))(&b"ababx"[..]);
+    assert_eq!(r, Ok((&b"x"[..], ())));
+    assert_eq!(buf, vec!['b', 'b']);
+
+    buf.clear();
+    let capacity = buf.capacity();
+    let r = fold_many0(elem, || (), |(), o| buf.push(o))(&b"abx"[..]);
+    assert_eq!(r, Ok((&b"x"[..], ())));
+    assert_eq!(buf, vec!['b']);
+    assert_eq!(buf.capacity(), capacity);
+  }
+
+  #[test]
+  fn parser_methods() {
+    let r: IResult<&str, u32> = char('a').map(|c| c as u32).parse("ab");
+    assert_eq!(r, Ok(("b", 97)));
+
+    let r: IResult<&str, char> = complete::take(2).and_then(char('b')).parse("bcd");
+    assert_eq!(r, Ok(("d", 'b')));
+    let r: IResult<&str, char> = complete::take(2).and_then(char('c')).parse("bcd");
+    assert_eq!(r, Err(Err::Error(("bc", 0))));
+
+    let r: IResult<&str, char> = char('a').or(char('b')).parse("bc");
+    assert_eq!(r, Ok(("c", 'b')));
+    let r: IResult<&str, Vec<char>> = char('a').many0().parse("aab");
+    assert_eq!(r, Ok(("b", vec!['a', 'a'])));
+  }
+
+  #[test]
+  fn parser_or_keeps_farthest_error() {
+    let mut p = pair(char('a'), char('b')).map(|_| ()).or(char('c').map(|_| ()));
+    match p.parse("ax") {
+      Err(Err::Error(e)) => assert_eq!(trace(&e), vec![("x", "E(Char)".to_string())]),
+      r => panic!("unexpected {:?}", r),
+    }
+  }
+
+  #[test]
+  fn parser_context() {
+    let r: IResult<&str, char, Verbose<&str>> = char('a').context("letter").parse("b");
+    match r {
+      Err(Err::Error(e)) => assert_eq!(trace(&e), vec![
+        ("b", "E(Char)".to_string()),
+        ("b", "Context(\"letter\")".to_string()),
+      ]),
+      r => panic!("unexpected {:?}", r),
+    }
+
+    let r: IResult<&str, char, Verbose<&str>> = cut(char('a')).context("letter").parse("b");
+    match r {
+      Err(Err::Failure(e)) => assert_eq!(e.v.len(), 2),
+      r => panic!("unexpected {:?}", r),
+    }
+  }
+
+  #[test]
+  fn pair_terminated_outcomes() {
+    let r: R<(char, char)> = pair(char('a'), char('b'))(&b"abx"[..]);
+    assert_eq!(r, Ok((&b"x"[..], ('a', 'b'))));
+    let r: R<(char, char)> = pair(char('a'), char('b'))(&b"xb"[..]);
+    assert_eq!(r, Err(Err::Error((&b"xb"[..], 0))));
+    let r: R<(char, char)> = pair(char('a'), char('b'))(&b"ax"[..]);
+    assert_eq!(r, Err(Err::Error((&b"x"[..], 0))));
+
+    let r: R<char> = terminated(char('a'), char('b'))(&b"abx"[..]);
+    assert_eq!(r, Ok((&b"x"[..], 'a')));
+    let r: R<char> = terminated(char('a'), char('b'))(&b"ax"[..]);
+    assert_eq!(r, Err(Err::Error((&b"x"[..], 0))));
+    let r: R<char> = terminated(cut(char('a')), char('b'))(&b"x"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"x"[..], 0))));
+    let r: IResult<P, char> = terminated(char('a'), char('b'))(p(b"a"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+  }
+
+  // the position and kind of an error
+  fn simple<I, O>(r: IResult<I, O, Simple<I>>) -> (I, ErrorKind) {
+    match r {
+      Err(Err::Error(e)) => (e.i, e.e),
+      _ => panic!("expected an error"),
+    }
+  }
+
+  #[test]
+  fn many_m_n_outcomes() {
+    let r: R<Vec<char>> = many_m_n(1, 2, char('a'))(&b"aaab"[..]);
+    assert_eq!(r, Ok((&b"ab"[..], vec!['a', 'a'])));
+    let r: R<Vec<char>> = many_m_n(0, 2, char('a'))(&b"b"[..]);
+    assert_eq!(r, Ok((&b"b"[..], vec![])));
+    assert_eq!(simple(many_m_n(2, 3, char('a'))(&b"ab"[..])), (&b"b"[..], ErrorKind::ManyMN));
+    // `m > n` can never succeed
+    assert_eq!(simple(many_m_n(3, 2, char('a'))(&b"aaaa"[..])), (&b"aa"[..], ErrorKind::ManyMN));
+
+    let r: R<Vec<char>> = many_m_n(0, 3, elem)(&b"abacx"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"cx"[..], 0))));
+    let r: IResult<P, Vec<char>> = many_m_n(1, 3, char('a'))(p(b"aa"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Unknown)));
+  }
+
+  #[test]
+  fn count_outcomes() {
+    let r: R<Vec<char>> = count(char('a'), 2)(&b"aaab"[..]);
+    assert_eq!(r, Ok((&b"ab"[..], vec!['a', 'a'])));
+    assert_eq!(simple(count(char('a'), 3)(&b"aab"[..])), (&b"b"[..], ErrorKind::Count));
+    let r: R<Vec<char>> = count(elem, 2)(&b"abacx"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"cx"[..], 0))));
+  }
+
+  #[test]
+  fn many_till_outcomes() {
+    let r: R<(Vec<char>, char)> = many_till(char('a'), char('b'))(&b"aabx"[..]);
+    assert_eq!(r, Ok((&b"x"[..], (vec!['a', 'a'], 'b'))));
+    assert_eq!(simple(many_till(char('a'), char('b'))(&b"aacx"[..])), (&b"cx"[..], ErrorKind::ManyTill));
+    // `f` succeeding without consuming anything would loop forever
+    assert_eq!(simple(many_till(opt(char('a')), char('b'))(&b"cx"[..])), (&b"cx"[..], ErrorKind::ManyTill));
+
+    let r: IResult<P, (Vec<char>, &[u8])> = many_till(char('a'), tag("bc").map(|t: P| t.into_inner()))(p(b"aab"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+  }
+
+  #[test]
+  fn opt_cond_peek_outcomes() {
+    let r: R<Option<char>> = opt(char('a'))(&b"ab"[..]);
+    assert_eq!(r, Ok((&b"b"[..], Some('a'))));
+    let r: R<Option<char>> = opt(char('a'))(&b"b"[..]);
+    assert_eq!(r, Ok((&b"b"[..], None)));
+    let r: R<Option<char>> = opt(elem)(&b"ac"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"c"[..], 0))));
+    let r: IResult<P, Option<char>> = opt(char('a'))(p(b""));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+
+    let r: R<Option<char>> = cond(true, char('a'))(&b"ab"[..]);
+    assert_eq!(r, Ok((&b"b"[..], Some('a'))));
+    let r: R<Option<char>> = cond(true, char('a'))(&b"b"[..]);
+    assert_eq!(r, Err(Err::Error((&b"b"[..], 0))));
+    let r: R<Option<char>> = cond(false, char('a'))(&b"b"[..]);
+    assert_eq!(r, Ok((&b"b"[..], None)));
+
+    let r: R<char> = peek(char('a'))(&b"ab"[..]);
+    assert_eq!(r, Ok((&b"ab"[..], 'a')));
+    let r: R<char> = peek(char('a'))(&b"b"[..]);
+    assert_eq!(r, Err(Err::Error((&b"b"[..], 0))));
+  }
+
+  #[test]
+  fn not_outcomes() {
+    let r: R<()> = not(char('a'))(&b"b"[..]);
+    assert_eq!(r, Ok((&b"b"[..], ())));
+    assert_eq!(simple(not(char('a'))(&b"ab"[..])), (&b"ab"[..], ErrorKind::Not));
+    let r: R<()> = not(elem)(&b"ac"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"c"[..], 0))));
+  }
+
+  #[test]
+  fn eof_outcomes() {
+    let r: R<&[u8]> = eof(&b""[..]);
+    assert_eq!(r, Ok((&b""[..], &b""[..])));
+    assert_eq!(simple(eof::<_, Simple<_>>(&b"a"[..])), (&b"a"[..], ErrorKind::Eof));
+    let r: IResult<P, P> = eof(p(b""));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Unknown)));
+    let r: IResult<P, P> = eof(p(b"a"));
+    assert_eq!(r, Err(Err::Error((p(b"a"), 0))));
+
+    let r: R<char> = all_consuming(char('a'))(&b"a"[..]);
+    assert_eq!(r, Ok((&b""[..], 'a')));
+    assert_eq!(simple(all_consuming(char('a'))(&b"ab"[..])), (&b"b"[..], ErrorKind::Eof));
+  }
+
+  #[test]
+  fn rest_outcomes() {
+    let r: R<&[u8]> = rest(&b"ab"[..]);
+    assert_eq!(r, Ok((&b""[..], &b"ab"[..])));
+    let r: IResult<&str, &str> = rest("");
+    assert_eq!(r, Ok(("", "")));
+    let r: IResult<P, P> = rest(p(b"ab"));
+    assert_eq!(r, Ok((p(b""), p(b"ab"))));
+  }
+
+  #[test]
+  fn verify_outcomes() {
+    let r: R<u8> = verify(complete::one_of(|_: u8| true), |c: &u8| *c != b'b')(&b"ab"[..]);
+    assert_eq!(r, Ok((&b"b"[..], b'a')));
+    let r: R<u8> = verify(complete::one_of(|_: u8| true), |c: &u8| *c != b'b')(&b"ba"[..]);
+    assert_eq!(r, Err(Err::Error((&b"ba"[..], 0))));
+    // the error points at the start of the rejected element
+    let r = verify(pair(char('a'), char('b')), |_: &(char, char)| false)(&b"abc"[..]);
+    assert_eq!(simple(r), (&b"abc"[..], ErrorKind::Verify));
+  }
+
+  #[test]
+  fn map_res_map_opt_outcomes() {
+    fn digits<'a, E: Er<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
+      complete::take_while1(|c: char| c.is_ascii_digit())(i)
+    }
+
+    let r: IResult<&str, u8> = map_res(digits, |s: &str| s.parse::<u8>())("12a");
+    assert_eq!(r, Ok(("a", 12)));
+    assert_eq!(simple(map_res(digits, |s: &str| s.parse::<u8>())("300a")), ("300a", ErrorKind::MapRes));
+    // the conversion error can be any type
+    assert_eq!(simple(map_res(digits, |_: &str| Err::<u8, _>(()))("1")), ("1", ErrorKind::MapRes));
+    assert_eq!(simple(map_res(digits, |_: &str| Err::<u8, _>("nope"))("1")), ("1", ErrorKind::MapRes));
+
+    let r: IResult<&str, u8, Verbose<&str>> = map_res_err(digits, |s: &str| s.parse::<u8>())("300a");
+    match r {
+      Err(Err::Error(e)) => {
+        let t = trace(&e);
+        assert_eq!(t.len(), 1);
+        assert_eq!(t[0].0, "300a");
+        assert!(t[0].1.starts_with("External(MapRes, ParseIntError"), "{}", t[0].1);
+      },
+      r => panic!("unexpected {:?}", r),
+    }
+
+    let r: IResult<&str, u8> = map_opt(digits, |s: &str| s.parse::<u8>().ok())("12a");
+    assert_eq!(r, Ok(("a", 12)));
+    assert_eq!(simple(map_opt(digits, |s: &str| s.parse::<u8>().ok())("300a")), ("300a", ErrorKind::MapOpt));
+  }
+
+  #[test]
+  fn recognize_consumed_outcomes() {
+    let r: R<&[u8]> = recognize(pair(char('a'), char('b')))(&b"abc"[..]);
+    assert_eq!(r, Ok((&b"c"[..], &b"ab"[..])));
+    let r: R<(&[u8], char)> = consumed(preceded(char('a'), char('b')))(&b"abc"[..]);
+    assert_eq!(r, Ok((&b"c"[..], (&b"ab"[..], 'b'))));
+    let r: R<&[u8]> = recognize(pair(char('a'), char('b')))(&b"ac"[..]);
+    assert_eq!(r, Err(Err::Error((&b"c"[..], 0))));
+
+    let r: IResult<&str, &str> = recognize(many0(char('é')))("ééa");
+    assert_eq!(r, Ok(("a", "éé")));
+    let r: IResult<&str, (&str, Vec<char>)> = consumed(many0(char('é')))("ééa");
+    assert_eq!(r, Ok(("a", ("éé", vec!['é', 'é']))));
+
+    let r: IResult<P, P> = recognize(pair(char('a'), char('b')))(p(b"abc"));
+    assert_eq!(r, Ok((p(b"c"), p(b"ab"))));
+    let r: IResult<P, P> = recognize(pair(char('a'), char('b')))(p(b"a"));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+  }
+
+  #[test]
+  fn recognize_consumed_span() {
+    let span = LocatedSpan::new("ab\ncde");
+    let (span, _) = complete::tag::<_, _, (_, u32)>("ab\n")(span).unwrap();
+
+    let r: IResult<_, _> = recognize(pair(char('c'), char('d')))(span);
+    let (rest, o) = r.unwrap();
+    assert_eq!((*o.fragment(), o.location_offset(), o.location_line(), o.get_column()), ("cd", 3, 2, 1));
+    assert_eq!((*rest.fragment(), rest.location_offset(), rest.location_line(), rest.get_column()), ("e", 5, 2, 3));
+
+    // the consumed span keeps the position where the parser started
+    let r: IResult<_, _> = consumed(pair(char('c'), char('d')))(span);
+    let (rest, (o, _)) = r.unwrap();
+    assert_eq!((*o.fragment(), o.location_offset(), o.location_line(), o.get_column()), ("cd", 3, 2, 1));
+    assert_eq!(rest.location_offset(), 5);
+  }
+}
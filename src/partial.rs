@@ -0,0 +1,383 @@
+//! Input wrapper carrying a completeness flag
+//!
+//! The primitives re-exported at the crate root (`char`, `tag`,
+//! `take_while`, `take`, ...) and the repetition combinators check that
+//! flag: on a partial input they behave like the `streaming` versions and
+//! return `Incomplete` when they reach the end of the data, otherwise they
+//! behave like the `complete` versions.
+use std::borrow::Cow;
+
+use crate::{IResult, Er, Parser, streaming, complete};
+use crate::traits::*;
+
+/// wraps an input to indicate whether more data can arrive after it
+///
+/// when a parser returns `Incomplete` and the stream has reached its end,
+/// call it again with `input.into_complete()` to get the final result
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Partial<I> {
+  input: I,
+  partial: bool,
+}
+
+impl<I> Partial<I> {
+  /// wraps an input that can be followed by more data
+  pub fn new(input: I) -> Self {
+    Partial { input, partial: true }
+  }
+
+  /// marks the input as complete, to use once the end of the stream was
+  /// reached
+  pub fn into_complete(self) -> Self {
+    Partial { input: self.input, partial: false }
+  }
+
+  pub fn into_inner(self) -> I {
+    self.input
+  }
+}
+
+impl<I> InputIsPartial for Partial<I> {
+  #[inline]
+  fn is_partial(&self) -> bool {
+    self.partial
+  }
+
+  #[inline]
+  fn into_complete(self) -> Self {
+    Partial::into_complete(self)
+  }
+}
+
+impl<I: InputLength> InputLength for Partial<I> {
+  #[inline]
+  fn input_len(&self) -> usize {
+    self.input.input_len()
+  }
+}
+
+impl<I: InputTake> InputTake for Partial<I> {
+  #[inline]
+  fn take(&self, count: usize) -> Self {
+    Partial { input: self.input.take(count), partial: self.partial }
+  }
+
+  #[inline]
+  fn take_split(&self, count: usize) -> (Self, Self) {
+    let (suffix, prefix) = self.input.take_split(count);
+    (Partial { input: suffix, partial: self.partial }, Partial { input: prefix, partial: self.partial })
+  }
+}
+
+impl<I: InputIter> InputIter for Partial<I> {
+  type Item = I::Item;
+  type Iter = I::Iter;
+  type IterElem = I::IterElem;
+
+  #[inline]
+  fn iter_indices(&self) -> Self::Iter {
+    self.input.iter_indices()
+  }
+
+  #[inline]
+  fn iter_elements(&self) -> Self::IterElem {
+    self.input.iter_elements()
+  }
+
+  #[inline]
+  fn position<P>(&self, predicate: P) -> Option<usize>
+    where P: Fn(Self::Item) -> bool {
+    self.input.position(predicate)
+  }
+
+  #[inline]
+  fn slice_index(&self, count: usize) -> Option<usize> {
+    self.input.slice_index(count)
+  }
+}
+
+impl<I: InputTakeAtPosition> InputTakeAtPosition for Partial<I> {
+  type Item = I::Item;
+
+  #[inline]
+  fn split_at_position<P>(&self, predicate: P) -> Option<(Self, Self)>
+    where P: Fn(Self::Item) -> bool {
+    let partial = self.partial;
+    self.input.split_at_position(predicate).map(|(suffix, prefix)| {
+      (Partial { input: suffix, partial }, Partial { input: prefix, partial })
+    })
+  }
+
+  #[inline]
+  fn split_at_position_complete<P>(&self, predicate: P) -> (Self, Self)
+    where P: Fn(Self::Item) -> bool {
+    let (suffix, prefix) = self.input.split_at_position_complete(predicate);
+    (Partial { input: suffix, partial: self.partial }, Partial { input: prefix, partial: self.partial })
+  }
+}
+
+impl<I: Compare<T>, T> Compare<T> for Partial<I> {
+  #[inline]
+  fn compare(&self, t: T) -> CompareResult {
+    self.input.compare(t)
+  }
+}
+
+impl<I: Offset> Offset for Partial<I> {
+  #[inline]
+  fn offset(&self, second: &Self) -> usize {
+    self.input.offset(&second.input)
+  }
+}
+
+impl<I: CompareNoCase<T>, T> CompareNoCase<T> for Partial<I> {
+  #[inline]
+  fn compare_no_case(&self, t: T) -> (CompareResult, usize) {
+    self.input.compare_no_case(t)
+  }
+}
+
+impl<I: FindSubstring<T>, T> FindSubstring<T> for Partial<I> {
+  #[inline]
+  fn find_substring(&self, substr: T) -> Option<usize> {
+    self.input.find_substring(substr)
+  }
+}
+
+impl<I: AsBytes> AsBytes for Partial<I> {
+  #[inline]
+  fn as_bytes(&self) -> &[u8] {
+    self.input.as_bytes()
+  }
+}
+
+impl<'a, I: ToCow<'a>> ToCow<'a> for Partial<I> {
+  type Target = I::Target;
+
+  #[inline]
+  fn to_cow(self) -> Cow<'a, I::Target> {
+    self.input.to_cow()
+  }
+}
+
+impl<I: ExtendInto<C>, C> ExtendInto<C> for Partial<I> {
+  #[inline]
+  fn extend_into(self, acc: &mut C) {
+    self.input.extend_into(acc)
+  }
+}
+
+impl<I: Slice<R>, R> Slice<R> for Partial<I> {
+  #[inline]
+  fn slice(&self, range: R) -> Self {
+    Partial { input: self.input.slice(range), partial: self.partial }
+  }
+}
+
+/// recognizes a single character, see `complete::char` and
+/// `streaming::char`
+pub fn char<I, E: Er<I>>(c: char) -> impl Fn(I) -> IResult<I, char, E>
+  where I: for<'b> Compare<&'b [u8]> + InputTake + InputLength + InputIsPartial {
+  let streaming = streaming::char(c);
+  let complete = complete::char(c);
+
+  move |i: I| {
+    if i.is_partial() {
+      streaming(i)
+    } else {
+      complete(i)
+    }
+  }
+}
+
+/// recognizes a fixed sequence, see `complete::tag` and `streaming::tag`
+pub fn tag<T, I, E: Er<I>>(t: T) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTake + InputLength + Compare<T> + InputIsPartial,
+        T: InputLength + Clone {
+  let streaming = streaming::tag(t.clone());
+  let complete = complete::tag(t);
+
+  move |i: I| {
+    if i.is_partial() {
+      streaming(i)
+    } else {
+      complete(i)
+    }
+  }
+}
+
+/// like `tag`, but ignores case, see `complete::tag_no_case` and
+/// `streaming::tag_no_case`
+pub fn tag_no_case<T, I, E: Er<I>>(t: T) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTake + CompareNoCase<T> + InputIsPartial,
+        T: Clone {
+  let streaming = streaming::tag_no_case(t.clone());
+  let complete = complete::tag_no_case(t);
+
+  move |i: I| {
+    if i.is_partial() {
+      streaming(i)
+    } else {
+      complete(i)
+    }
+  }
+}
+
+/// returns the longest prefix whose elements match the predicate, see
+/// `complete::take_while` and `streaming::take_while`
+pub fn take_while<I, F, E: Er<I>>(cond: F) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTakeAtPosition + InputIsPartial,
+        F: Predicate<<I as InputTakeAtPosition>::Item> {
+  move |i: I| {
+    if i.is_partial() {
+      streaming::take_while(|c: <I as InputTakeAtPosition>::Item| cond.test(c))(i)
+    } else {
+      complete::take_while(|c: <I as InputTakeAtPosition>::Item| cond.test(c))(i)
+    }
+  }
+}
+
+/// like `take_while`, but fails if the first element does not match
+pub fn take_while1<I, F, E: Er<I>>(cond: F) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTakeAtPosition + InputLength + InputIsPartial,
+        F: Predicate<<I as InputTakeAtPosition>::Item> {
+  move |i: I| {
+    if i.is_partial() {
+      streaming::take_while1(|c: <I as InputTakeAtPosition>::Item| cond.test(c))(i)
+    } else {
+      complete::take_while1(|c: <I as InputTakeAtPosition>::Item| cond.test(c))(i)
+    }
+  }
+}
+
+/// returns the first `count` elements of the input, see `complete::take`
+/// and `streaming::take`
+pub fn take<I, E: Er<I>>(count: usize) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputIter + InputTake + InputIsPartial {
+  move |i: I| {
+    if i.is_partial() {
+      streaming::take(count)(i)
+    } else {
+      complete::take(count)(i)
+    }
+  }
+}
+
+/// returns the input up to the first occurrence of a sequence, see
+/// `complete::take_until` and `streaming::take_until`
+pub fn take_until<T, I, E: Er<I>>(t: T) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTake + FindSubstring<T> + AsBytes + InputIsPartial,
+        T: AsBytes + Clone {
+  move |i: I| {
+    if i.is_partial() {
+      streaming::take_until(t.clone())(i)
+    } else {
+      complete::take_until(t.clone())(i)
+    }
+  }
+}
+
+/// returns the longest prefix whose elements do not match the predicate,
+/// see `complete::take_till` and `streaming::take_till`
+pub fn take_till<I, F, E: Er<I>>(cond: F) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTakeAtPosition + InputIsPartial,
+        F: Predicate<<I as InputTakeAtPosition>::Item> {
+  move |i: I| {
+    if i.is_partial() {
+      streaming::take_till(|c: <I as InputTakeAtPosition>::Item| cond.test(c))(i)
+    } else {
+      complete::take_till(|c: <I as InputTakeAtPosition>::Item| cond.test(c))(i)
+    }
+  }
+}
+
+/// gets a length from the first parser, then returns that many elements,
+/// see `complete::length_data` and `streaming::length_data`
+pub fn length_data<I, N, E: Er<I>, F>(mut f: F) -> impl FnMut(I) -> IResult<I, I, E>
+  where I: InputIter + InputTake + InputIsPartial,
+        N: ToUsize,
+        F: Parser<I, N, E> {
+  move |i: I| {
+    let (i, length) = f.parse(i)?;
+    take(length.to_usize())(i)
+  }
+}
+
+/// gets a length from the first parser, then applies the second one on
+/// that many elements, see `complete::length_value` and
+/// `streaming::length_value`
+///
+/// on a partial input, the second parser gets those elements marked as
+/// complete, since no more data can arrive for them
+pub fn length_value<I, N, O, E: Er<I>, F, G>(mut f: F, mut g: G) -> impl FnMut(I) -> IResult<I, O, E>
+  where I: InputIter + InputTake + InputIsPartial,
+        N: ToUsize,
+        F: Parser<I, N, E>,
+        G: Parser<I, O, E> {
+  move |i: I| {
+    let (i, data) = length_data(|i| f.parse(i))(i)?;
+    let (_, o) = g.parse(data.into_complete())?;
+    Ok((i, o))
+  }
+}
+
+/// recognizes one element that is in the set, see `complete::one_of` and
+/// `streaming::one_of`
+pub fn one_of<I, P, E: Er<I>>(set: P) -> impl Fn(I) -> IResult<I, <I as InputIter>::Item, E>
+  where I: InputIter + InputTake + InputIsPartial,
+        <I as InputIter>::Item: Copy,
+        P: Predicate<<I as InputIter>::Item> {
+  move |i: I| {
+    if i.is_partial() {
+      streaming::one_of(|c: <I as InputIter>::Item| set.test(c))(i)
+    } else {
+      complete::one_of(|c: <I as InputIter>::Item| set.test(c))(i)
+    }
+  }
+}
+
+/// recognizes one element that is not in the set, see `complete::none_of`
+/// and `streaming::none_of`
+pub fn none_of<I, P, E: Er<I>>(set: P) -> impl Fn(I) -> IResult<I, <I as InputIter>::Item, E>
+  where I: InputIter + InputTake + InputIsPartial,
+        <I as InputIter>::Item: Copy,
+        P: Predicate<<I as InputIter>::Item> {
+  move |i: I| {
+    if i.is_partial() {
+      streaming::none_of(|c: <I as InputIter>::Item| set.test(c))(i)
+    } else {
+      complete::none_of(|c: <I as InputIter>::Item| set.test(c))(i)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Err, Needed, many0};
+
+  type P<'a> = Partial<&'a [u8]>;
+
+  fn len(i: P) -> IResult<P, u8> {
+    one_of(|_: u8| true)(i)
+  }
+
+  #[test]
+  fn length_value_complete_data() {
+    // the data stops at the announced length, `g` must not ask for more
+    let r: IResult<P, P> = length_value(len, take_while(|c| c == b'a'))(Partial::new(&b"\x02aaZZ"[..]));
+    assert_eq!(r, Ok((Partial::new(&b"ZZ"[..]), Partial::new(&b"aa"[..]).into_complete())));
+    let r: IResult<P, Vec<P>> = length_value(len, many0(tag("ab")))(Partial::new(&b"\x04ababZZ"[..]));
+    let (i, o) = r.unwrap();
+    assert_eq!(i, Partial::new(&b"ZZ"[..]));
+    assert_eq!(o.len(), 2);
+
+    let r: IResult<P, P> = length_value(len, tag("abc"))(Partial::new(&b"\x02abZZ"[..]));
+    assert_eq!(r, Err(Err::Error((Partial::new(&b"ab"[..]).into_complete(), 0))));
+    // the data itself is still incomplete
+    let r: IResult<P, P> = length_value(len, tag("ab"))(Partial::new(&b"\x03ab"[..]));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: IResult<P, P> = length_value(len, tag("ab"))(Partial::new(&b""[..]));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+  }
+}
@@ -0,0 +1,156 @@
+//! Parsers stored on the heap, and recursive grammars
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use crate::{IResult, Parser};
+
+/// a parser behind a `Box`, so parsers of different types can be stored in
+/// a struct or a `Vec`, or chosen at runtime. `'a` is the lifetime of the
+/// data the parser borrows, usually that of the input it works on
+pub struct BoxedParser<'a, I, O, E> {
+  parser: Box<dyn Parser<I, O, E> + 'a>,
+}
+
+impl<'a, I, O, E> BoxedParser<'a, I, O, E> {
+  pub fn new<P: Parser<I, O, E> + 'a>(parser: P) -> Self {
+    BoxedParser { parser: Box::new(parser) }
+  }
+}
+
+impl<'a, I, O, E> Parser<I, O, E> for BoxedParser<'a, I, O, E> {
+  #[inline]
+  fn parse(&mut self, input: I) -> IResult<I, O, E> {
+    self.parser.parse(input)
+  }
+}
+
+type Factory<'a, I, O, E> = dyn Fn(RecursiveRef<'a, I, O, E>) -> BoxedParser<'a, I, O, E> + 'a;
+
+struct Inner<'a, I, O, E> {
+  factory: Box<Factory<'a, I, O, E>>,
+  // instances that are not running. A parser can be called again while
+  // it runs, so each nesting level takes its own instance
+  idle: RefCell<Vec<BoxedParser<'a, I, O, E>>>,
+}
+
+impl<'a, I, O, E> Inner<'a, I, O, E> {
+  fn parse(self: &Rc<Self>, input: I) -> IResult<I, O, E> {
+    let idle = self.idle.borrow_mut().pop();
+    let mut parser = match idle {
+      Some(parser) => parser,
+      None => (self.factory)(RecursiveRef { inner: Rc::downgrade(self) }),
+    };
+
+    let res = parser.parse(input);
+    self.idle.borrow_mut().push(parser);
+    res
+  }
+}
+
+/// a parser that can refer to itself, see `recursive`
+pub struct Recursive<'a, I, O, E> {
+  inner: Rc<Inner<'a, I, O, E>>,
+}
+
+impl<'a, I, O, E> Parser<I, O, E> for Recursive<'a, I, O, E> {
+  fn parse(&mut self, input: I) -> IResult<I, O, E> {
+    self.inner.parse(input)
+  }
+}
+
+/// the handle a recursive parser uses to call itself, see `recursive`
+///
+/// it does not keep the parser alive: using it after the `Recursive` was
+/// dropped panics
+pub struct RecursiveRef<'a, I, O, E> {
+  inner: Weak<Inner<'a, I, O, E>>,
+}
+
+impl<'a, I, O, E> Clone for RecursiveRef<'a, I, O, E> {
+  fn clone(&self) -> Self {
+    RecursiveRef { inner: self.inner.clone() }
+  }
+}
+
+impl<'a, I, O, E> Parser<I, O, E> for RecursiveRef<'a, I, O, E> {
+  fn parse(&mut self, input: I) -> IResult<I, O, E> {
+    let inner = self.inner.upgrade().expect("the Recursive parser was dropped");
+    inner.parse(input)
+  }
+}
+
+/// builds a parser that refers to itself. `f` gets a handle to the parser
+/// being defined and returns its body:
+///
+/// ```no_run
+/// # use nomfun::*;
+/// enum Tree<'a> {
+///   Leaf(&'a str),
+///   List(Vec<Tree<'a>>),
+/// }
+///
+/// fn tree<'a>(input: &'a str) -> IResult<&'a str, Tree<'a>> {
+///   let mut tree = recursive(|tree| {
+///     alt((
+///       delimited(char('('), separated_list0(char(','), tree), char(')')).map(Tree::List),
+///       take_while1(|c: char| c.is_alphanumeric()).map(Tree::Leaf),
+///     ))
+///   });
+///   tree.parse(input)
+/// }
+/// ```
+///
+/// `f` can be called more than once: a new instance of the body is built
+/// when the parser is called while it is already running, one per nesting
+/// level. The instances are kept for the next calls
+pub fn recursive<'a, I, O, E, F, P>(f: F) -> Recursive<'a, I, O, E>
+  where F: Fn(RecursiveRef<'a, I, O, E>) -> P + 'a,
+        P: Parser<I, O, E> + 'a {
+  let factory = move |r| BoxedParser::new(f(r));
+  Recursive {
+    inner: Rc::new(Inner {
+      factory: Box::new(factory),
+      idle: RefCell::new(Vec::new()),
+    }),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Err, alt, char, complete, delimited, separated_list0, take_while1};
+
+  // counts the leaves of a nested list like `(a,(b,c),())`
+  fn leaves(input: &str) -> IResult<&str, usize> {
+    let mut list = recursive(|list| {
+      delimited(
+        char('('),
+        separated_list0(char(','), alt((list, take_while1(|c: char| c.is_alphabetic()).map(|_| 1)))),
+        char(')')
+      ).map(|v: Vec<usize>| v.iter().sum())
+    });
+    list.parse(input)
+  }
+
+  #[test]
+  fn recursive_on_borrowed_input() {
+    let data = String::from("(a,(b,c),(),((d)))rest");
+    assert_eq!(leaves(&data), Ok(("rest", 4)));
+    assert_eq!(leaves("(a,(b"), Err(Err::Error((",(b", 0))));
+  }
+
+  type CharParser<'a> = BoxedParser<'a, &'a [u8], char, (&'a [u8], u32)>;
+
+  #[test]
+  fn boxed_on_borrowed_input() {
+    let data = [b'a', b'b'];
+    let mut parsers: Vec<CharParser> = vec![
+      BoxedParser::new(complete::char('a')),
+      complete::char('b').boxed(),
+    ];
+
+    let (i, a) = parsers[0].parse(&data[..]).unwrap();
+    let (i, b) = parsers[1].parse(i).unwrap();
+    assert_eq!((i, a, b), (&b""[..], 'a', 'b'));
+  }
+}
@@ -0,0 +1,98 @@
+//! Sequences of parsers
+use crate::{IResult, Er, Parser};
+
+/// implemented for tuples of 2 to 21 parsers, see `tuple`
+pub trait Tuple<I, O, E> {
+  /// applies the parsers in order and returns a tuple of their results
+  fn parse(&mut self, input: I) -> IResult<I, O, E>;
+}
+
+/// applies a tuple of parsers one after the other, and returns their
+/// results as a tuple
+///
+/// the first error is returned as is
+pub fn tuple<I, O, E: Er<I>, List: Tuple<I, O, E>>(mut l: List) -> impl FnMut(I) -> IResult<I, O, E> {
+  move |i: I| l.parse(i)
+}
+
+macro_rules! tuple_trait(
+  ($name1:ident $ty1:ident, $name2: ident $ty2:ident, $($name:ident $ty:ident),*) => (
+    tuple_trait!(__impl $name1 $ty1, $name2 $ty2; $($name $ty),*);
+  );
+  (__impl $($name:ident $ty: ident),+; $name1:ident $ty1:ident, $($name2:ident $ty2:ident),*) => (
+    tuple_trait_impl!($($name $ty),+);
+    tuple_trait!(__impl $($name $ty),+ , $name1 $ty1; $($name2 $ty2),*);
+  );
+  (__impl $($name:ident $ty: ident),+; $name1:ident $ty1:ident) => (
+    tuple_trait_impl!($($name $ty),+);
+    tuple_trait_impl!($($name $ty),+, $name1 $ty1);
+  );
+);
+
+macro_rules! tuple_trait_impl(
+  ($($name:ident $ty: ident),+) => (
+    impl<
+      Input, $($ty),+ , Error: Er<Input>,
+      $($name: Parser<Input, $ty, Error>),+
+    > Tuple<Input, ( $($ty),+ ), Error> for ( $($name),+ ) {
+
+      #[inline]
+      fn parse(&mut self, input: Input) -> IResult<Input, ( $($ty),+ ), Error> {
+        tuple_trait_inner!(0, self, input, (), $($name)+)
+      }
+    }
+  );
+);
+
+macro_rules! tuple_trait_inner(
+  ($it:tt, $self:expr, $input:expr, (), $head:ident $($id:ident)+) => ({
+    let (i, o) = $self.$it.parse($input)?;
+
+    succ!($it, tuple_trait_inner!($self, i, ( o ), $($id)+))
+  });
+  ($it:tt, $self:expr, $input:expr, ($($parsed:tt)*), $head:ident $($id:ident)+) => ({
+    let (i, o) = $self.$it.parse($input)?;
+
+    succ!($it, tuple_trait_inner!($self, i, ($($parsed)* , o), $($id)+))
+  });
+  ($it:tt, $self:expr, $input:expr, ($($parsed:tt)*), $head:ident) => ({
+    let (i, o) = $self.$it.parse($input)?;
+
+    Ok((i, ($($parsed)* , o)))
+  });
+);
+
+tuple_trait!(FnA A, FnB B, FnC C, FnD D, FnE E, FnF F, FnG G, FnH H, FnI I, FnJ J, FnK K, FnL L,
+  FnM M, FnN N, FnO O, FnP P, FnQ Q, FnR R, FnS S, FnT T, FnU U);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Err, Needed, Partial, char, cut, tag};
+  use crate::test_util::R;
+
+  #[test]
+  fn tuple_outcomes() {
+    let r: R<(char, &[u8], char)> = tuple((char('a'), tag("bc"), char('d')))(&b"abcdx"[..]);
+    assert_eq!(r, Ok((&b"x"[..], ('a', &b"bc"[..], 'd'))));
+    // the first error is returned as is
+    let r: R<(char, &[u8], char)> = tuple((char('a'), tag("bc"), char('d')))(&b"abx"[..]);
+    assert_eq!(r, Err(Err::Error((&b"bx"[..], 0))));
+    let r: R<(char, char, char)> = tuple((char('a'), cut(char('b')), char('c')))(&b"ax"[..]);
+    assert_eq!(r, Err(Err::Failure((&b"x"[..], 0))));
+    let r: IResult<Partial<&[u8]>, (char, char)> = tuple((char('a'), char('b')))(Partial::new(&b"a"[..]));
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+  }
+
+  #[test]
+  fn tuple_21_parsers() {
+    let r: R<_> = tuple((
+      char('a'), char('b'), char('c'), char('d'), char('e'), char('f'), char('g'),
+      char('h'), char('i'), char('j'), char('k'), char('l'), char('m'), char('n'),
+      char('o'), char('p'), char('q'), char('r'), char('s'), char('t'), char('u'),
+    ))(&b"abcdefghijklmnopqrstux"[..]);
+    let (i, o) = r.unwrap();
+    assert_eq!(i, &b"x"[..]);
+    assert_eq!((o.0, o.10, o.20), ('a', 'k', 'u'));
+  }
+}
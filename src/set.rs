@@ -0,0 +1,137 @@
+//! Sets of bytes built at compile time
+use crate::traits::Predicate;
+
+/// a set of bytes stored as a 256 bit bitmap, so testing an element is a
+/// single lookup. It can be built in a `const`:
+///
+/// `const DIGIT: ByteSet = ByteSet::from_range(b'0', b'9');`
+///
+/// it can be given to `take_while`, `one_of` and the other primitives
+/// that take a predicate. On `&str` input, only ASCII chars can match
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ByteSet {
+  bits: [u64; 4],
+}
+
+impl ByteSet {
+  /// the empty set
+  pub const fn new() -> Self {
+    ByteSet { bits: [0; 4] }
+  }
+
+  /// the set of the bytes in `bytes`, like `ByteSet::from_bytes(b" \t")`
+  pub const fn from_bytes(bytes: &[u8]) -> Self {
+    let mut bits = [0; 4];
+    let mut i = 0;
+    while i < bytes.len() {
+      let b = bytes[i];
+      bits[(b >> 6) as usize] |= 1 << (b & 63);
+      i += 1;
+    }
+
+    ByteSet { bits }
+  }
+
+  /// the set of the bytes from `start` to `end`, both included
+  pub const fn from_range(start: u8, end: u8) -> Self {
+    let mut bits = [0; 4];
+    let mut b = start as usize;
+    while b <= end as usize {
+      bits[b >> 6] |= 1 << (b & 63);
+      b += 1;
+    }
+
+    ByteSet { bits }
+  }
+
+  pub const fn union(self, other: ByteSet) -> Self {
+    let a = self.bits;
+    let b = other.bits;
+    ByteSet { bits: [a[0] | b[0], a[1] | b[1], a[2] | b[2], a[3] | b[3]] }
+  }
+
+  /// the bytes of `self` that are not in `other`
+  pub const fn difference(self, other: ByteSet) -> Self {
+    let a = self.bits;
+    let b = other.bits;
+    ByteSet { bits: [a[0] & !b[0], a[1] & !b[1], a[2] & !b[2], a[3] & !b[3]] }
+  }
+
+  /// the bytes that are not in `self`
+  pub const fn complement(self) -> Self {
+    let a = self.bits;
+    ByteSet { bits: [!a[0], !a[1], !a[2], !a[3]] }
+  }
+
+  #[inline]
+  pub const fn contains(&self, b: u8) -> bool {
+    (self.bits[(b >> 6) as usize] >> (b & 63)) & 1 == 1
+  }
+}
+
+impl Default for ByteSet {
+  fn default() -> Self {
+    ByteSet::new()
+  }
+}
+
+impl Predicate<u8> for ByteSet {
+  #[inline]
+  fn test(&self, c: u8) -> bool {
+    self.contains(c)
+  }
+}
+
+impl Predicate<char> for ByteSet {
+  #[inline]
+  fn test(&self, c: char) -> bool {
+    c.is_ascii() && self.contains(c as u8)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Err, IResult, complete};
+
+  // the token set from the HTTP benchmark, and the match it replaced
+  const TOKEN: ByteSet = ByteSet::from_range(33, 127)
+    .difference(ByteSet::from_bytes(b"()<>@,;:\\\"/[]?={}"));
+
+  fn is_token(c: u8) -> bool {
+    !matches!(c,
+      128..=255 | 0..=31 | b'(' | b')' | b'<' | b'>' | b'@' | b',' | b';' | b':'
+      | b'\\' | b'"' | b'/' | b'[' | b']' | b'?' | b'=' | b'{' | b'}' | b' ')
+  }
+
+  #[test]
+  fn token_matches_old_predicate() {
+    for b in 0..=255u8 {
+      assert_eq!(TOKEN.contains(b), is_token(b), "byte {}", b);
+    }
+  }
+
+  #[test]
+  fn range_bounds() {
+    let high = ByteSet::from_range(250, 255);
+    assert!((0..=255u8).all(|b| high.contains(b) == (b >= 250)));
+    let all = ByteSet::from_range(0, 255);
+    assert_eq!(all, ByteSet::new().complement());
+    let one = ByteSet::from_range(63, 64);
+    assert!((0..=255u8).all(|b| one.contains(b) == (b == 63 || b == 64)));
+    assert_eq!(ByteSet::from_range(1, 0), ByteSet::new());
+  }
+
+  #[test]
+  fn non_ascii_chars() {
+    // `é` is 0xE9 as a code point, the set has no way to hold it
+    let all = ByteSet::new().complement();
+    assert!(!Predicate::<char>::test(&all, 'é'));
+    assert!(Predicate::<char>::test(&all, 'e'));
+
+    let r: IResult<&str, &str> = complete::take_while(all)("abé");
+    assert_eq!(r, Ok(("é", "ab")));
+    let r: IResult<&str, char> = complete::one_of(all)("é");
+    assert_eq!(r, Err(Err::Error(("é", 0))));
+  }
+}
@@ -0,0 +1,288 @@
+//! Input wrapper tracking the position in the original data
+use std::ops::{Range, RangeFrom, RangeFull, RangeTo};
+use std::borrow::Cow;
+
+use crate::traits::*;
+
+/// wraps a `&[u8]` or `&str` input and keeps track of its byte offset, line
+/// and column in the original data, as the parsers consume it
+///
+/// lines and columns start at 1, the column is counted in bytes
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LocatedSpan<T> {
+  fragment: T,
+  offset: usize,
+  line: u32,
+  column: usize,
+}
+
+impl<T> LocatedSpan<T> {
+  /// starts tracking positions at the beginning of `fragment`
+  pub fn new(fragment: T) -> Self {
+    LocatedSpan { fragment, offset: 0, line: 1, column: 1 }
+  }
+
+  /// the part of the input this span covers
+  pub fn fragment(&self) -> &T {
+    &self.fragment
+  }
+
+  /// byte offset from the start of the original input
+  pub fn location_offset(&self) -> usize {
+    self.offset
+  }
+
+  pub fn location_line(&self) -> u32 {
+    self.line
+  }
+
+  pub fn get_column(&self) -> usize {
+    self.column
+  }
+
+  pub fn into_fragment(self) -> T {
+    self.fragment
+  }
+}
+
+impl<T: AsBytes> LocatedSpan<T> {
+  /// position of the data starting `count` bytes into this span
+  fn advance(&self, count: usize, fragment: T) -> Self {
+    let consumed = &self.fragment.as_bytes()[..count];
+
+    let (line, column) = match consumed.iter().rposition(|&b| b == b'\n') {
+      None => (self.line, self.column + count),
+      Some(last) => {
+        let newlines = consumed.iter().filter(|&&b| b == b'\n').count();
+        (self.line + newlines as u32, count - last)
+      }
+    };
+
+    LocatedSpan { fragment, offset: self.offset + count, line, column }
+  }
+
+  fn with_fragment(&self, fragment: T) -> Self {
+    LocatedSpan { fragment, offset: self.offset, line: self.line, column: self.column }
+  }
+}
+
+impl<T: AsBytes> AsBytes for LocatedSpan<T> {
+  #[inline]
+  fn as_bytes(&self) -> &[u8] {
+    self.fragment.as_bytes()
+  }
+}
+
+impl<T: InputIsPartial> InputIsPartial for LocatedSpan<T> {
+  #[inline]
+  fn is_partial(&self) -> bool {
+    self.fragment.is_partial()
+  }
+
+  #[inline]
+  fn into_complete(self) -> Self {
+    let fragment = self.fragment.into_complete();
+    LocatedSpan { fragment, ..self }
+  }
+}
+
+impl<T: InputLength> InputLength for LocatedSpan<T> {
+  #[inline]
+  fn input_len(&self) -> usize {
+    self.fragment.input_len()
+  }
+}
+
+impl<T: InputTake + AsBytes> InputTake for LocatedSpan<T> {
+  #[inline]
+  fn take(&self, count: usize) -> Self {
+    self.with_fragment(self.fragment.take(count))
+  }
+
+  #[inline]
+  fn take_split(&self, count: usize) -> (Self, Self) {
+    let (suffix, prefix) = self.fragment.take_split(count);
+    (self.advance(count, suffix), self.with_fragment(prefix))
+  }
+}
+
+impl<T: InputIter> InputIter for LocatedSpan<T> {
+  type Item = T::Item;
+  type Iter = T::Iter;
+  type IterElem = T::IterElem;
+
+  #[inline]
+  fn iter_indices(&self) -> Self::Iter {
+    self.fragment.iter_indices()
+  }
+
+  #[inline]
+  fn iter_elements(&self) -> Self::IterElem {
+    self.fragment.iter_elements()
+  }
+
+  #[inline]
+  fn position<P>(&self, predicate: P) -> Option<usize>
+    where P: Fn(Self::Item) -> bool {
+    self.fragment.position(predicate)
+  }
+
+  #[inline]
+  fn slice_index(&self, count: usize) -> Option<usize> {
+    self.fragment.slice_index(count)
+  }
+}
+
+impl<T: InputTakeAtPosition + AsBytes> InputTakeAtPosition for LocatedSpan<T> {
+  type Item = T::Item;
+
+  #[inline]
+  fn split_at_position<P>(&self, predicate: P) -> Option<(Self, Self)>
+    where P: Fn(Self::Item) -> bool {
+    self.fragment.split_at_position(predicate).map(|(suffix, prefix)| {
+      let count = prefix.as_bytes().len();
+      (self.advance(count, suffix), self.with_fragment(prefix))
+    })
+  }
+
+  #[inline]
+  fn split_at_position_complete<P>(&self, predicate: P) -> (Self, Self)
+    where P: Fn(Self::Item) -> bool {
+    let (suffix, prefix) = self.fragment.split_at_position_complete(predicate);
+    let count = prefix.as_bytes().len();
+    (self.advance(count, suffix), self.with_fragment(prefix))
+  }
+}
+
+impl<T: Compare<U>, U> Compare<U> for LocatedSpan<T> {
+  #[inline]
+  fn compare(&self, t: U) -> CompareResult {
+    self.fragment.compare(t)
+  }
+}
+
+impl<T: Offset> Offset for LocatedSpan<T> {
+  #[inline]
+  fn offset(&self, second: &Self) -> usize {
+    self.fragment.offset(&second.fragment)
+  }
+}
+
+impl<T: CompareNoCase<U>, U> CompareNoCase<U> for LocatedSpan<T> {
+  #[inline]
+  fn compare_no_case(&self, t: U) -> (CompareResult, usize) {
+    self.fragment.compare_no_case(t)
+  }
+}
+
+impl<T: FindSubstring<U>, U> FindSubstring<U> for LocatedSpan<T> {
+  #[inline]
+  fn find_substring(&self, substr: U) -> Option<usize> {
+    self.fragment.find_substring(substr)
+  }
+}
+
+impl<'a, T: ToCow<'a>> ToCow<'a> for LocatedSpan<T> {
+  type Target = T::Target;
+
+  #[inline]
+  fn to_cow(self) -> Cow<'a, T::Target> {
+    self.fragment.to_cow()
+  }
+}
+
+impl<T: ExtendInto<C>, C> ExtendInto<C> for LocatedSpan<T> {
+  #[inline]
+  fn extend_into(self, acc: &mut C) {
+    self.fragment.extend_into(acc)
+  }
+}
+
+macro_rules! slice_range_impl {
+  ( $range:ty, $start:expr ) => {
+    impl<T: Slice<$range> + AsBytes> Slice<$range> for LocatedSpan<T> {
+      #[inline]
+      fn slice(&self, range: $range) -> Self {
+        let start: fn(&$range) -> usize = $start;
+        let count = start(&range);
+        self.advance(count, self.fragment.slice(range))
+      }
+    }
+  };
+}
+
+slice_range_impl!(Range<usize>, |r| r.start);
+slice_range_impl!(RangeTo<usize>, |_| 0);
+slice_range_impl!(RangeFrom<usize>, |r| r.start);
+slice_range_impl!(RangeFull, |_| 0);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Err, IResult, Simple, complete, pair};
+
+  // fragment, offset, line and column
+  fn pos<T: Copy>(s: &LocatedSpan<T>) -> (T, usize, u32, usize) {
+    (*s.fragment(), s.location_offset(), s.location_line(), s.get_column())
+  }
+
+  #[test]
+  fn take_split_across_lines() {
+    let span = LocatedSpan::new(&b"ab\ncd\nef"[..]);
+    let (suffix, prefix) = span.take_split(7);
+    assert_eq!(pos(&prefix), (&b"ab\ncd\ne"[..], 0, 1, 1));
+    assert_eq!(pos(&suffix), (&b"f"[..], 7, 3, 2));
+
+    let (suffix, _) = suffix.take_split(0);
+    assert_eq!(pos(&suffix), (&b"f"[..], 7, 3, 2));
+    let (suffix, _) = span.take_split(3);
+    assert_eq!(pos(&suffix), (&b"cd\nef"[..], 3, 2, 1));
+  }
+
+  #[test]
+  fn split_at_position_across_lines() {
+    let span = LocatedSpan::new("ab\ncd\nef");
+    let (suffix, prefix) = span.split_at_position(|c| c == 'e').unwrap();
+    assert_eq!(pos(&prefix), ("ab\ncd\n", 0, 1, 1));
+    assert_eq!(pos(&suffix), ("ef", 6, 3, 1));
+
+    assert!(span.split_at_position(|_| false).is_none());
+    let (suffix, prefix) = span.split_at_position_complete(|_| false);
+    assert_eq!(pos(&prefix), ("ab\ncd\nef", 0, 1, 1));
+    assert_eq!(pos(&suffix), ("", 8, 3, 3));
+  }
+
+  #[test]
+  fn slice_across_lines() {
+    let span = LocatedSpan::new("ab\ncd\nef");
+    assert_eq!(pos(&span.slice(3..5)), ("cd", 3, 2, 1));
+    assert_eq!(pos(&span.slice(4..)), ("d\nef", 4, 2, 2));
+    assert_eq!(pos(&span.slice(..2)), ("ab", 0, 1, 1));
+    assert_eq!(pos(&span.slice(..)), ("ab\ncd\nef", 0, 1, 1));
+    assert_eq!(pos(&span.slice(3..5).slice(1..)), ("d", 4, 2, 2));
+  }
+
+  #[test]
+  fn multi_byte_columns() {
+    // columns are counted in bytes, not in chars
+    let span = LocatedSpan::new("é\nàb");
+    let r: IResult<_, _> = complete::tag("é")(span);
+    let (rest, _) = r.unwrap();
+    assert_eq!(pos(&rest), ("\nàb", 2, 1, 3));
+
+    let r: IResult<_, _> = complete::take(2usize)(rest);
+    let (rest, taken) = r.unwrap();
+    assert_eq!(pos(&taken), ("\nà", 2, 1, 3));
+    assert_eq!(pos(&rest), ("b", 5, 2, 3));
+  }
+
+  #[test]
+  fn error_position() {
+    let span = LocatedSpan::new(&b"ab\ncd"[..]);
+    let r: IResult<_, _, Simple<_>> = pair(complete::tag("ab\nc"), complete::char('x'))(span);
+    match r {
+      Err(Err::Error(e)) => assert_eq!(pos(&e.i), (&b"d"[..], 4, 2, 2)),
+      r => panic!("unexpected {:?}", r),
+    }
+  }
+}
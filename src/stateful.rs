@@ -0,0 +1,242 @@
+//! Input wrapper carrying user state
+use std::borrow::Cow;
+
+use crate::traits::*;
+
+/// wraps an input along with user state, like a symbol table or a
+/// counter, that parsers can read and update as they consume the input:
+///
+/// ```no_run
+/// # use nomfun::*;
+/// fn item(i: Stateful<&str, u32>) -> IResult<Stateful<&str, u32>, char> {
+///   let (mut i, c) = char('a')(i)?;
+///   *i.state_mut() += 1;
+///   Ok((i, c))
+/// }
+/// ```
+///
+/// the state is cloned along with the input, so when `or` or `alt` go back
+/// to a previous input, the changes made by the failed branch are dropped.
+/// It is also cloned into the slices returned by the primitives, so a
+/// parser clones it several times per element. Keep a large state, like a
+/// symbol table, behind an `Rc` and update it with `Rc::make_mut`: clones
+/// only copy the pointer, and the table itself is copied only when it is
+/// changed while an earlier input still refers to it
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Stateful<I, S> {
+  input: I,
+  state: S,
+}
+
+impl<I, S> Stateful<I, S> {
+  pub fn new(input: I, state: S) -> Self {
+    Stateful { input, state }
+  }
+
+  pub fn state(&self) -> &S {
+    &self.state
+  }
+
+  pub fn state_mut(&mut self) -> &mut S {
+    &mut self.state
+  }
+
+  pub fn input(&self) -> &I {
+    &self.input
+  }
+
+  pub fn into_inner(self) -> I {
+    self.input
+  }
+
+  /// returns the input and the state
+  pub fn into_parts(self) -> (I, S) {
+    (self.input, self.state)
+  }
+}
+
+impl<I: InputIsPartial, S> InputIsPartial for Stateful<I, S> {
+  #[inline]
+  fn is_partial(&self) -> bool {
+    self.input.is_partial()
+  }
+
+  #[inline]
+  fn into_complete(self) -> Self {
+    Stateful { input: self.input.into_complete(), state: self.state }
+  }
+}
+
+impl<I: InputLength, S> InputLength for Stateful<I, S> {
+  #[inline]
+  fn input_len(&self) -> usize {
+    self.input.input_len()
+  }
+}
+
+impl<I: InputTake, S: Clone> InputTake for Stateful<I, S> {
+  #[inline]
+  fn take(&self, count: usize) -> Self {
+    Stateful { input: self.input.take(count), state: self.state.clone() }
+  }
+
+  #[inline]
+  fn take_split(&self, count: usize) -> (Self, Self) {
+    let (suffix, prefix) = self.input.take_split(count);
+    (Stateful { input: suffix, state: self.state.clone() }, Stateful { input: prefix, state: self.state.clone() })
+  }
+}
+
+impl<I: InputIter, S> InputIter for Stateful<I, S> {
+  type Item = I::Item;
+  type Iter = I::Iter;
+  type IterElem = I::IterElem;
+
+  #[inline]
+  fn iter_indices(&self) -> Self::Iter {
+    self.input.iter_indices()
+  }
+
+  #[inline]
+  fn iter_elements(&self) -> Self::IterElem {
+    self.input.iter_elements()
+  }
+
+  #[inline]
+  fn position<P>(&self, predicate: P) -> Option<usize>
+    where P: Fn(Self::Item) -> bool {
+    self.input.position(predicate)
+  }
+
+  #[inline]
+  fn slice_index(&self, count: usize) -> Option<usize> {
+    self.input.slice_index(count)
+  }
+}
+
+impl<I: InputTakeAtPosition, S: Clone> InputTakeAtPosition for Stateful<I, S> {
+  type Item = I::Item;
+
+  #[inline]
+  fn split_at_position<P>(&self, predicate: P) -> Option<(Self, Self)>
+    where P: Fn(Self::Item) -> bool {
+    self.input.split_at_position(predicate).map(|(suffix, prefix)| {
+      (Stateful { input: suffix, state: self.state.clone() }, Stateful { input: prefix, state: self.state.clone() })
+    })
+  }
+
+  #[inline]
+  fn split_at_position_complete<P>(&self, predicate: P) -> (Self, Self)
+    where P: Fn(Self::Item) -> bool {
+    let (suffix, prefix) = self.input.split_at_position_complete(predicate);
+    (Stateful { input: suffix, state: self.state.clone() }, Stateful { input: prefix, state: self.state.clone() })
+  }
+}
+
+impl<I: Compare<T>, T, S> Compare<T> for Stateful<I, S> {
+  #[inline]
+  fn compare(&self, t: T) -> CompareResult {
+    self.input.compare(t)
+  }
+}
+
+impl<I: CompareNoCase<T>, T, S> CompareNoCase<T> for Stateful<I, S> {
+  #[inline]
+  fn compare_no_case(&self, t: T) -> (CompareResult, usize) {
+    self.input.compare_no_case(t)
+  }
+}
+
+impl<I: FindSubstring<T>, T, S> FindSubstring<T> for Stateful<I, S> {
+  #[inline]
+  fn find_substring(&self, substr: T) -> Option<usize> {
+    self.input.find_substring(substr)
+  }
+}
+
+impl<I: Offset, S> Offset for Stateful<I, S> {
+  #[inline]
+  fn offset(&self, second: &Self) -> usize {
+    self.input.offset(&second.input)
+  }
+}
+
+impl<I: AsBytes, S> AsBytes for Stateful<I, S> {
+  #[inline]
+  fn as_bytes(&self) -> &[u8] {
+    self.input.as_bytes()
+  }
+}
+
+impl<'a, I: ToCow<'a>, S> ToCow<'a> for Stateful<I, S> {
+  type Target = I::Target;
+
+  #[inline]
+  fn to_cow(self) -> Cow<'a, I::Target> {
+    self.input.to_cow()
+  }
+}
+
+impl<I: ExtendInto<C>, C, S> ExtendInto<C> for Stateful<I, S> {
+  #[inline]
+  fn extend_into(self, acc: &mut C) {
+    self.input.extend_into(acc)
+  }
+}
+
+impl<I: Slice<R>, R, S: Clone> Slice<R> for Stateful<I, S> {
+  #[inline]
+  fn slice(&self, range: R) -> Self {
+    Stateful { input: self.input.slice(range), state: self.state.clone() }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::rc::Rc;
+  use crate::{IResult, Err, Parser, char, escaped, many0};
+
+  type S<'a> = Stateful<&'a str, u32>;
+
+  fn item(i: S) -> IResult<S, char> {
+    let (mut i, c) = char('a')(i)?;
+    *i.state_mut() += 1;
+    Ok((i, c))
+  }
+
+  #[test]
+  fn state_through_combinators() {
+    let (i, v) = many0(item)(Stateful::new("aab", 0)).unwrap();
+    assert_eq!((i.into_parts(), v.len()), (("b", 2), 2));
+
+    let (i, _) = escaped(item, '\\', item)(Stateful::new("aa\\ab", 0)).unwrap();
+    assert_eq!(i.into_parts(), ("b", 3));
+  }
+
+  #[test]
+  fn state_backtracks() {
+    // the first branch counts an `a` before failing on the `c`
+    let mut p = (|i| { let (i, _) = item(i)?; char('b')(i) }).or(char('a'));
+    let (i, _) = p.parse(Stateful::new("ac", 0)).unwrap();
+    assert_eq!(i.into_parts(), ("c", 0));
+
+    let r = p.parse(Stateful::new("c", 0));
+    assert_eq!(r, Err(Err::Error((Stateful::new("c", 0), 0))));
+  }
+
+  #[test]
+  fn shared_state() {
+    fn define(i: Stateful<&str, Rc<Vec<char>>>) -> IResult<Stateful<&str, Rc<Vec<char>>>, ()> {
+      let (mut i, c) = char('a').or(char('b')).parse(i)?;
+      Rc::make_mut(i.state_mut()).push(c);
+      Ok((i, ()))
+    }
+
+    let start = Stateful::new("abx", Rc::new(vec![]));
+    let (i, _) = many0(define)(start.clone()).unwrap();
+    assert_eq!(i.state().as_slice(), &['a', 'b']);
+    // the starting input still sees the state it had
+    assert!(start.state().is_empty());
+  }
+}
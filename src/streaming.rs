@@ -0,0 +1,343 @@
+//! Primitives for data that arrives in chunks
+//!
+//! When the input ends before a primitive can decide whether it matched,
+//! it returns `Err::Incomplete`: the caller should read more data and run
+//! the parser again on the bigger buffer.
+use crate::{IResult, Err, Needed, ErrorKind, Er, Parser, complete};
+use crate::traits::*;
+
+/// recognizes a single character, see `complete::char`
+///
+/// returns `Incomplete` if the input is empty or holds only the start of
+/// the character's UTF-8 encoding, with the number of missing bytes
+pub fn char<I, E: Er<I>>(c: char) -> impl Fn(I) -> IResult<I, char, E>
+  where I: for<'b> Compare<&'b [u8]> + InputTake + InputLength {
+
+  let mut buf = [0; 4];
+  let len = c.encode_utf8(&mut buf).len();
+
+  move |i: I| {
+    match i.compare(&buf[..len]) {
+      CompareResult::Ok => Ok((i.take_split(len).0, c)),
+      CompareResult::Incomplete => Err(Err::Incomplete(Needed::Size(len - i.input_len()))),
+      CompareResult::Error => Err(Err::Error(E::from_error_kind(i, ErrorKind::Char))),
+    }
+  }
+}
+
+/// recognizes a fixed sequence, see `complete::tag`
+///
+/// returns `Incomplete` if the input is a prefix of the tag, with the
+/// number of missing bytes
+pub fn tag<T, I, E: Er<I>>(t: T) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTake + InputLength + Compare<T>,
+        T: InputLength + Clone {
+  move |i: I| {
+    let tag_len = t.input_len();
+    match i.compare(t.clone()) {
+      CompareResult::Ok => Ok(i.take_split(tag_len)),
+      CompareResult::Incomplete => Err(Err::Incomplete(Needed::Size(tag_len - i.input_len()))),
+      CompareResult::Error => Err(Err::Error(E::from_error_kind(i, ErrorKind::Tag))),
+    }
+  }
+}
+
+/// like `tag`, but ignores case, see `complete::tag_no_case`
+///
+/// returns `Incomplete` if the input is a prefix of the tag, with the
+/// number of missing bytes
+pub fn tag_no_case<T, I, E: Er<I>>(t: T) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTake + CompareNoCase<T>,
+        T: Clone {
+  move |i: I| {
+    match i.compare_no_case(t.clone()) {
+      (CompareResult::Ok, len) => Ok(i.take_split(len)),
+      (CompareResult::Incomplete, needed) => Err(Err::Incomplete(Needed::Size(needed))),
+      (CompareResult::Error, _) => Err(Err::Error(E::from_error_kind(i, ErrorKind::Tag))),
+    }
+  }
+}
+
+/// returns the longest prefix whose elements match the predicate, see
+/// `complete::take_while`
+///
+/// returns `Incomplete(Needed::Size(1))` if every element of the input
+/// matched, since the next chunk could continue the match
+pub fn take_while<I, F, E: Er<I>>(cond: F) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTakeAtPosition,
+        F: Predicate<<I as InputTakeAtPosition>::Item> {
+  move |input: I| {
+    match input.split_at_position(|c| !cond.test(c)) {
+      Some(s) => Ok(s),
+      None => Err(Err::Incomplete(Needed::Size(1))),
+    }
+  }
+}
+
+/// like `take_while`, but fails if the first element does not match
+///
+/// returns `Incomplete(Needed::Size(1))` if every element of the input
+/// matched, including on empty input
+pub fn take_while1<I, F, E: Er<I>>(cond: F) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTakeAtPosition + InputLength,
+        F: Predicate<<I as InputTakeAtPosition>::Item> {
+  move |input: I| {
+    match input.split_at_position(|c| !cond.test(c)) {
+      None => Err(Err::Incomplete(Needed::Size(1))),
+      Some(s) => if s.1.input_len() == 0 {
+        Err(Err::Error(E::from_error_kind(input, ErrorKind::TakeWhile1)))
+      } else {
+        Ok(s)
+      }
+    }
+  }
+}
+
+/// returns the first `count` elements of the input
+///
+/// returns `Incomplete` with the number of missing elements if the input
+/// is too short. On `&str`, elements are chars, so the missing count is
+/// the minimum number of bytes to read
+pub fn take<I, E: Er<I>>(count: usize) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputIter + InputTake {
+  move |i: I| {
+    match i.slice_index(count) {
+      Some(index) => Ok(i.take_split(index)),
+      None => Err(Err::Incomplete(Needed::Size(count - i.iter_elements().count()))),
+    }
+  }
+}
+
+/// gets a length from the first parser, then returns that many elements
+///
+/// returns `Incomplete` if the length parser does, or with the number of
+/// missing elements if the data is not all there yet
+pub fn length_data<I, N, E: Er<I>, F>(mut f: F) -> impl FnMut(I) -> IResult<I, I, E>
+  where I: InputIter + InputTake,
+        N: ToUsize,
+        F: Parser<I, N, E> {
+  move |i: I| {
+    let (i, length) = f.parse(i)?;
+    take(length.to_usize())(i)
+  }
+}
+
+/// returns the input up to the first occurrence of `t`, which is not
+/// consumed
+///
+/// returns `Incomplete` if `t` was not found, with the number of bytes
+/// needed to complete it if the input ends with its start
+pub fn take_until<T, I, E: Er<I>>(t: T) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTake + FindSubstring<T> + AsBytes,
+        T: AsBytes + Clone {
+  move |i: I| {
+    match i.find_substring(t.clone()) {
+      Some(index) => Ok(i.take_split(index)),
+      None => {
+        let data = i.as_bytes();
+        let needle = t.as_bytes();
+        // the longest end of the data that could be the start of `t`
+        let overlap = (1..needle.len().min(data.len() + 1)).rev()
+          .find(|&k| data.ends_with(&needle[..k]))
+          .unwrap_or(0);
+
+        Err(Err::Incomplete(Needed::Size(needle.len() - overlap)))
+      }
+    }
+  }
+}
+
+/// returns the longest prefix whose elements do not match the predicate
+///
+/// returns `Incomplete(Needed::Size(1))` if no element matched
+pub fn take_till<I, F, E: Er<I>>(cond: F) -> impl Fn(I) -> IResult<I, I, E>
+  where I: InputTakeAtPosition,
+        F: Predicate<<I as InputTakeAtPosition>::Item> {
+  move |input: I| {
+    match input.split_at_position(|c| cond.test(c)) {
+      Some(s) => Ok(s),
+      None => Err(Err::Incomplete(Needed::Size(1))),
+    }
+  }
+}
+
+/// gets a length from the first parser, then applies the second one on
+/// that many elements. The second parser does not have to consume all of
+/// them
+///
+/// returns `Incomplete` if the length parser does, or with the number of
+/// missing elements if the data is not all there yet. The data given to
+/// the second parser is all there is, so an `Incomplete` from it is
+/// returned as an error
+pub fn length_value<I, N, O, E: Er<I>, F, G>(mut f: F, mut g: G) -> impl FnMut(I) -> IResult<I, O, E>
+  where I: Clone + InputIter + InputTake,
+        N: ToUsize,
+        F: Parser<I, N, E>,
+        G: Parser<I, O, E> {
+  move |i: I| {
+    let (i, data) = length_data(|i| f.parse(i))(i)?;
+    match g.parse(data.clone()) {
+      Ok((_, o)) => Ok((i, o)),
+      Err(Err::Incomplete(_)) => Err(Err::Error(E::from_error_kind(data, ErrorKind::LengthValue))),
+      Err(e) => Err(e),
+    }
+  }
+}
+
+/// recognizes one element that is in the set, see `complete::one_of`
+///
+/// returns `Incomplete(Needed::Size(1))` on empty input
+pub fn one_of<I, P, E: Er<I>>(set: P) -> impl Fn(I) -> IResult<I, <I as InputIter>::Item, E>
+  where I: InputIter + InputTake,
+        <I as InputIter>::Item: Copy,
+        P: Predicate<<I as InputIter>::Item> {
+  move |i: I| {
+    if i.iter_elements().next().is_none() {
+      return Err(Err::Incomplete(Needed::Size(1)));
+    }
+    complete::one_element(i, |c| set.test(c), ErrorKind::OneOf)
+  }
+}
+
+/// recognizes one element that is not in the set, see `complete::none_of`
+///
+/// returns `Incomplete(Needed::Size(1))` on empty input
+pub fn none_of<I, P, E: Er<I>>(set: P) -> impl Fn(I) -> IResult<I, <I as InputIter>::Item, E>
+  where I: InputIter + InputTake,
+        <I as InputIter>::Item: Copy,
+        P: Predicate<<I as InputIter>::Item> {
+  move |i: I| {
+    if i.iter_elements().next().is_none() {
+      return Err(Err::Incomplete(Needed::Size(1)));
+    }
+    complete::one_element(i, |c| !set.test(c), ErrorKind::NoneOf)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_util::{R, streaming_len_u8};
+
+  #[test]
+  fn char_incomplete() {
+    let r: R<char> = char('a')(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: R<char> = char('é')(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(2))));
+    let r: R<char> = char('é')(&b"\xc3"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: IResult<&str, char> = char('a')("");
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+
+    let r: R<char> = char('é')(&b"\xc3\xa9b"[..]);
+    assert_eq!(r, Ok((&b"b"[..], 'é')));
+    let r: R<char> = char('a')(&b"b"[..]);
+    assert_eq!(r, Err(Err::Error((&b"b"[..], 0))));
+  }
+
+  #[test]
+  fn tag_incomplete() {
+    let r: R<&[u8]> = tag("HTTP/")(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(5))));
+    let r: R<&[u8]> = tag("HTTP/")(&b"HT"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(3))));
+    let r: R<&[u8]> = tag("é")(&b"\xc3"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: IResult<&str, &str> = tag("GET")("GE");
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+
+    let r: R<&[u8]> = tag("HTTP/")(&b"HX"[..]);
+    assert_eq!(r, Err(Err::Error((&b"HX"[..], 0))));
+    let r: R<&[u8]> = tag("HTTP/")(&b"HTTP/1.1"[..]);
+    assert_eq!(r, Ok((&b"1.1"[..], &b"HTTP/"[..])));
+  }
+
+  #[test]
+  fn tag_no_case_incomplete() {
+    let r: R<&[u8]> = tag_no_case("get")(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(3))));
+    let r: R<&[u8]> = tag_no_case("get")(&b"G"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(2))));
+
+    let r: R<&[u8]> = tag_no_case("get")(&b"Got"[..]);
+    assert_eq!(r, Err(Err::Error((&b"Got"[..], 0))));
+    let r: R<&[u8]> = tag_no_case("get")(&b"GeT /"[..]);
+    assert_eq!(r, Ok((&b" /"[..], &b"GeT"[..])));
+  }
+
+  #[test]
+  fn take_while_incomplete() {
+    let r: R<&[u8]> = take_while(|c: u8| c == b'a')(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: R<&[u8]> = take_while(|c: u8| c == b'a')(&b"aa"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: R<&[u8]> = take_while(|c: u8| c == b'a')(&b"aab"[..]);
+    assert_eq!(r, Ok((&b"b"[..], &b"aa"[..])));
+
+    let r: R<&[u8]> = take_while1(|c: u8| c == b'a')(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: R<&[u8]> = take_while1(|c: u8| c == b'a')(&b"b"[..]);
+    assert_eq!(r, Err(Err::Error((&b"b"[..], 0))));
+
+    let r: R<&[u8]> = take_till(|c: u8| c == b';')(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: R<&[u8]> = take_till(|c: u8| c == b';')(&b"ab;"[..]);
+    assert_eq!(r, Ok((&b";"[..], &b"ab"[..])));
+  }
+
+  #[test]
+  fn take_incomplete() {
+    let r: R<&[u8]> = take(4)(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(4))));
+    let r: R<&[u8]> = take(4)(&b"ab"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(2))));
+    let r: IResult<&str, &str> = take(3)("é");
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(2))));
+    let r: R<&[u8]> = take(2)(&b"abc"[..]);
+    assert_eq!(r, Ok((&b"c"[..], &b"ab"[..])));
+  }
+
+  #[test]
+  fn take_until_incomplete() {
+    let r: R<&[u8]> = take_until("\r\n")(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(2))));
+    let r: R<&[u8]> = take_until("\r\n")(&b"abc"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(2))));
+    let r: R<&[u8]> = take_until("\r\n")(&b"abc\r"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: R<&[u8]> = take_until("\r\n")(&b"abc\r\n"[..]);
+    assert_eq!(r, Ok((&b"\r\n"[..], &b"abc"[..])));
+  }
+
+  #[test]
+  fn length_incomplete() {
+    let r: R<&[u8]> = length_data(streaming_len_u8)(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: R<&[u8]> = length_data(streaming_len_u8)(&b"\x03a"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(2))));
+    let r: R<&[u8]> = length_data(streaming_len_u8)(&b"\x03abcd"[..]);
+    assert_eq!(r, Ok((&b"d"[..], &b"abc"[..])));
+
+    let r: R<&[u8]> = length_value(streaming_len_u8, tag("ab"))(&b"\x03a"[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(2))));
+    // the second parser only gets the announced data
+    let r: R<&[u8]> = length_value(streaming_len_u8, tag("abc"))(&b"\x02abc"[..]);
+    assert_eq!(r, Err(Err::Error((&b"ab"[..], 0))));
+    let r: R<&[u8]> = length_value(streaming_len_u8, tag("ab"))(&b"\x03abcd"[..]);
+    assert_eq!(r, Ok((&b"d"[..], &b"ab"[..])));
+  }
+
+  #[test]
+  fn one_of_incomplete() {
+    let r: R<u8> = one_of(|c: u8| c == b'a')(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: R<u8> = one_of(|c: u8| c == b'a')(&b"b"[..]);
+    assert_eq!(r, Err(Err::Error((&b"b"[..], 0))));
+
+    let r: R<u8> = none_of(|c: u8| c == b'a')(&b""[..]);
+    assert_eq!(r, Err(Err::Incomplete(Needed::Size(1))));
+    let r: R<u8> = none_of(|c: u8| c == b'a')(&b"bc"[..]);
+    assert_eq!(r, Ok((&b"c"[..], b'b')));
+  }
+}
@@ -0,0 +1,20 @@
+//! fixtures shared by the unit tests
+
+use crate::{IResult, Verbose, complete, streaming};
+
+pub(crate) type R<'a, O> = IResult<&'a [u8], O>;
+
+/// one byte length prefix
+pub(crate) fn len_u8(i: &[u8]) -> R<'_, u8> {
+  complete::one_of(|_: u8| true)(i)
+}
+
+/// one byte length prefix, asking for more data on empty input
+pub(crate) fn streaming_len_u8(i: &[u8]) -> R<'_, u8> {
+  streaming::one_of(|_: u8| true)(i)
+}
+
+/// the positions and kinds recorded in a `Verbose` error
+pub(crate) fn trace<I: Copy>(e: &Verbose<I>) -> Vec<(I, String)> {
+  e.v.iter().map(|(i, k)| (*i, format!("{:?}", k))).collect()
+}
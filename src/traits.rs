@@ -0,0 +1,595 @@
+//! Traits the combinators use to manipulate their input
+//!
+//! They are implemented for `&[u8]`, `&str` and `&[T]`. Other input types
+//! can be used with nomfun by implementing them.
+use std::ops::{Range, RangeFrom, RangeFull, RangeTo};
+use std::iter::{Enumerate, Cloned};
+use std::str::{CharIndices, Chars};
+use std::slice::Iter;
+use std::mem;
+use std::borrow::Cow;
+
+/// abstracts the length of the input
+pub trait InputLength {
+  /// number of elements left in the input
+  fn input_len(&self) -> usize;
+}
+
+impl<T> InputLength for &[T] {
+  #[inline]
+  fn input_len(&self) -> usize {
+    self.len()
+  }
+}
+
+impl InputLength for &str {
+  #[inline]
+  fn input_len(&self) -> usize {
+    self.len()
+  }
+}
+
+/// splits the input at a fixed index
+pub trait InputTake: Sized {
+  /// returns a slice of `count` elements from the start of the input
+  fn take(&self, count: usize) -> Self;
+
+  /// splits the input at `count`, the result is ordered like an
+  /// `IResult`: `(remaining, taken)`
+  fn take_split(&self, count: usize) -> (Self, Self);
+}
+
+impl<T> InputTake for &[T] {
+  #[inline]
+  fn take(&self, count: usize) -> Self {
+    &self[0..count]
+  }
+
+  #[inline]
+  fn take_split(&self, count: usize) -> (Self, Self) {
+    let (prefix, suffix) = self.split_at(count);
+    (suffix, prefix)
+  }
+}
+
+impl InputTake for &str {
+  #[inline]
+  fn take(&self, count: usize) -> Self {
+    &self[..count]
+  }
+
+  #[inline]
+  fn take_split(&self, count: usize) -> (Self, Self) {
+    let (prefix, suffix) = self.split_at(count);
+    (suffix, prefix)
+  }
+}
+
+/// iterates over the elements of the input
+pub trait InputIter {
+  type Item;
+  type Iter: Iterator<Item=(usize, Self::Item)>;
+  type IterElem: Iterator<Item=Self::Item>;
+
+  /// iterates over the elements and their index in the input
+  fn iter_indices(&self) -> Self::Iter;
+  /// iterates over the elements
+  fn iter_elements(&self) -> Self::IterElem;
+  /// index of the first element matching the predicate
+  fn position<P>(&self, predicate: P) -> Option<usize>
+    where P: Fn(Self::Item) -> bool;
+  /// index of the element that comes after `count` elements, if there
+  /// are enough of them
+  fn slice_index(&self, count: usize) -> Option<usize>;
+}
+
+impl<'a, T: Clone> InputIter for &'a [T] {
+  type Item = T;
+  type Iter = Enumerate<Self::IterElem>;
+  type IterElem = Cloned<Iter<'a, T>>;
+
+  #[inline]
+  fn iter_indices(&self) -> Self::Iter {
+    self.iter_elements().enumerate()
+  }
+
+  #[inline]
+  fn iter_elements(&self) -> Self::IterElem {
+    self.iter().cloned()
+  }
+
+  #[inline]
+  fn position<P>(&self, predicate: P) -> Option<usize>
+    where P: Fn(Self::Item) -> bool {
+    self.iter().position(|c| predicate(c.clone()))
+  }
+
+  #[inline]
+  fn slice_index(&self, count: usize) -> Option<usize> {
+    if self.len() >= count {
+      Some(count)
+    } else {
+      None
+    }
+  }
+}
+
+impl<'a> InputIter for &'a str {
+  type Item = char;
+  type Iter = CharIndices<'a>;
+  type IterElem = Chars<'a>;
+
+  #[inline]
+  fn iter_indices(&self) -> Self::Iter {
+    self.char_indices()
+  }
+
+  #[inline]
+  fn iter_elements(&self) -> Self::IterElem {
+    self.chars()
+  }
+
+  #[inline]
+  fn position<P>(&self, predicate: P) -> Option<usize>
+    where P: Fn(Self::Item) -> bool {
+    self.char_indices().find(|&(_, c)| predicate(c)).map(|(i, _)| i)
+  }
+
+  #[inline]
+  fn slice_index(&self, count: usize) -> Option<usize> {
+    let mut cnt = 0;
+    for (index, _) in self.char_indices() {
+      if cnt == count {
+        return Some(index);
+      }
+      cnt += 1;
+    }
+    if cnt == count {
+      return Some(self.len());
+    }
+    None
+  }
+}
+
+/// splits the input at the first element matching a predicate
+pub trait InputTakeAtPosition: Sized {
+  type Item;
+
+  /// returns `(remaining, taken)`, split at the first element for which
+  /// the predicate returns true, or `None` if no element matched
+  fn split_at_position<P>(&self, predicate: P) -> Option<(Self, Self)>
+    where P: Fn(Self::Item) -> bool;
+
+  /// like `split_at_position`, but takes the whole input if no element
+  /// matched
+  fn split_at_position_complete<P>(&self, predicate: P) -> (Self, Self)
+    where P: Fn(Self::Item) -> bool;
+}
+
+impl<T: Clone> InputTakeAtPosition for &[T] {
+  type Item = T;
+
+  #[inline]
+  fn split_at_position<P>(&self, predicate: P) -> Option<(Self, Self)>
+    where P: Fn(Self::Item) -> bool {
+    self.position(predicate).map(|i| self.take_split(i))
+  }
+
+  #[inline]
+  fn split_at_position_complete<P>(&self, predicate: P) -> (Self, Self)
+    where P: Fn(Self::Item) -> bool {
+    match self.position(predicate) {
+      Some(i) => self.take_split(i),
+      None => self.take_split(self.len()),
+    }
+  }
+}
+
+impl InputTakeAtPosition for &str {
+  type Item = char;
+
+  #[inline]
+  fn split_at_position<P>(&self, predicate: P) -> Option<(Self, Self)>
+    where P: Fn(Self::Item) -> bool {
+    self.position(predicate).map(|i| self.take_split(i))
+  }
+
+  #[inline]
+  fn split_at_position_complete<P>(&self, predicate: P) -> (Self, Self)
+    where P: Fn(Self::Item) -> bool {
+    match self.position(predicate) {
+      Some(i) => self.take_split(i),
+      None => self.take_split(self.len()),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CompareResult {
+  Ok,
+  /// the input is a prefix of the value it is compared to
+  Incomplete,
+  Error,
+}
+
+/// compares the start of the input with a value, usually a tag
+pub trait Compare<T> {
+  fn compare(&self, t: T) -> CompareResult;
+}
+
+impl<'b, T: PartialEq> Compare<&'b [T]> for &[T] {
+  #[inline]
+  fn compare(&self, t: &'b [T]) -> CompareResult {
+    match self.iter().zip(t.iter()).position(|(a, b)| a != b) {
+      Some(_) => CompareResult::Error,
+      None => if self.len() >= t.len() {
+        CompareResult::Ok
+      } else {
+        CompareResult::Incomplete
+      },
+    }
+  }
+}
+
+impl<'b> Compare<&'b str> for &[u8] {
+  #[inline]
+  fn compare(&self, t: &'b str) -> CompareResult {
+    self.compare(t.as_bytes())
+  }
+}
+
+impl<'b> Compare<&'b [u8]> for &str {
+  #[inline]
+  fn compare(&self, t: &'b [u8]) -> CompareResult {
+    match self.as_bytes().compare(t) {
+      // a byte tag must not split a code point
+      CompareResult::Ok if !self.is_char_boundary(t.len()) => CompareResult::Error,
+      res => res,
+    }
+  }
+}
+
+impl<'b> Compare<&'b str> for &str {
+  #[inline]
+  fn compare(&self, t: &'b str) -> CompareResult {
+    self.as_bytes().compare(t.as_bytes())
+  }
+}
+
+/// compares the start of the input with a value, ignoring case, see
+/// `tag_no_case`
+pub trait CompareNoCase<T> {
+  /// like `Compare::compare`. Also returns the length of the input that
+  /// matched for `Ok`, since it can differ from the length of the value
+  /// on `&str`, and the length of the value left to match for
+  /// `Incomplete`
+  fn compare_no_case(&self, t: T) -> (CompareResult, usize);
+}
+
+impl<'b> CompareNoCase<&'b [u8]> for &[u8] {
+  fn compare_no_case(&self, t: &'b [u8]) -> (CompareResult, usize) {
+    match self.iter().zip(t.iter()).position(|(a, b)| !a.eq_ignore_ascii_case(b)) {
+      Some(_) => (CompareResult::Error, 0),
+      None => if self.len() >= t.len() {
+        (CompareResult::Ok, t.len())
+      } else {
+        (CompareResult::Incomplete, t.len() - self.len())
+      },
+    }
+  }
+}
+
+impl<'b> CompareNoCase<&'b str> for &[u8] {
+  #[inline]
+  fn compare_no_case(&self, t: &'b str) -> (CompareResult, usize) {
+    self.compare_no_case(t.as_bytes())
+  }
+}
+
+impl<'b> CompareNoCase<&'b str> for &str {
+  fn compare_no_case(&self, t: &'b str) -> (CompareResult, usize) {
+    let mut chars = self.chars();
+    let mut len = 0;
+
+    for (index, tc) in t.char_indices() {
+      match chars.next() {
+        None => return (CompareResult::Incomplete, t.len() - index),
+        Some(c) => if eq_simple_fold(c, tc) {
+          len += c.len_utf8();
+        } else {
+          return (CompareResult::Error, 0);
+        },
+      }
+    }
+
+    (CompareResult::Ok, len)
+  }
+}
+
+// std only has the full case mappings, which can produce several chars, so
+// this keeps the one to one mappings. Comparing both the lowercase and the
+// uppercase forms covers cases like 'ſ', which is only equal to 's' once
+// uppercased
+fn eq_simple_fold(a: char, b: char) -> bool {
+  fn single<I: Iterator<Item=char>>(mut it: I, c: char) -> char {
+    match (it.next(), it.next()) {
+      (Some(m), None) => m,
+      _ => c,
+    }
+  }
+
+  if a == b {
+    return true;
+  }
+  if a.is_ascii() && b.is_ascii() {
+    return a.eq_ignore_ascii_case(&b);
+  }
+
+  single(a.to_lowercase(), a) == single(b.to_lowercase(), b)
+    || single(a.to_uppercase(), a) == single(b.to_uppercase(), b)
+}
+
+/// finds the first occurrence of a sequence in the input, see `take_until`
+pub trait FindSubstring<T> {
+  /// index where `substr` starts
+  fn find_substring(&self, substr: T) -> Option<usize>;
+}
+
+impl<'b> FindSubstring<&'b [u8]> for &[u8] {
+  fn find_substring(&self, substr: &'b [u8]) -> Option<usize> {
+    if substr.is_empty() {
+      return Some(0);
+    }
+
+    self.windows(substr.len()).position(|w| w == substr)
+  }
+}
+
+impl<'b> FindSubstring<&'b str> for &[u8] {
+  #[inline]
+  fn find_substring(&self, substr: &'b str) -> Option<usize> {
+    self.find_substring(substr.as_bytes())
+  }
+}
+
+impl<'b> FindSubstring<&'b str> for &str {
+  #[inline]
+  fn find_substring(&self, substr: &'b str) -> Option<usize> {
+    self.find(substr)
+  }
+}
+
+/// slices the input with a range
+pub trait Slice<R> {
+  fn slice(&self, range: R) -> Self;
+}
+
+macro_rules! slice_range_impl {
+  ( $range:ty ) => {
+    impl<T> Slice<$range> for &[T] {
+      #[inline]
+      fn slice(&self, range: $range) -> Self {
+        &self[range]
+      }
+    }
+
+    impl Slice<$range> for &str {
+      #[inline]
+      fn slice(&self, range: $range) -> Self {
+        &self[range]
+      }
+    }
+  };
+}
+
+slice_range_impl!(Range<usize>);
+slice_range_impl!(RangeTo<usize>);
+slice_range_impl!(RangeFrom<usize>);
+slice_range_impl!(RangeFull);
+
+/// computes the distance between two inputs, where `second` is a
+/// suffix of `self`
+pub trait Offset {
+  /// number of elements from the start of `self` to the start of `second`
+  fn offset(&self, second: &Self) -> usize;
+}
+
+impl<T> Offset for &[T] {
+  #[inline]
+  fn offset(&self, second: &Self) -> usize {
+    let fst = self.as_ptr() as usize;
+    let snd = second.as_ptr() as usize;
+
+    (snd - fst) / mem::size_of::<T>().max(1)
+  }
+}
+
+impl Offset for &str {
+  #[inline]
+  fn offset(&self, second: &Self) -> usize {
+    let fst = self.as_ptr() as usize;
+    let snd = second.as_ptr() as usize;
+
+    snd - fst
+  }
+}
+
+/// tells whether more data can still arrive after the end of the input
+///
+/// slices are always complete, use `Partial` to parse a stream
+pub trait InputIsPartial {
+  fn is_partial(&self) -> bool;
+
+  /// the same input, marked as complete
+  fn into_complete(self) -> Self;
+}
+
+impl<T> InputIsPartial for &[T] {
+  #[inline]
+  fn is_partial(&self) -> bool {
+    false
+  }
+
+  #[inline]
+  fn into_complete(self) -> Self {
+    self
+  }
+}
+
+impl InputIsPartial for &str {
+  #[inline]
+  fn is_partial(&self) -> bool {
+    false
+  }
+
+  #[inline]
+  fn into_complete(self) -> Self {
+    self
+  }
+}
+
+/// gives access to the raw bytes of a text or byte input
+pub trait AsBytes {
+  fn as_bytes(&self) -> &[u8];
+}
+
+impl AsBytes for &[u8] {
+  #[inline]
+  fn as_bytes(&self) -> &[u8] {
+    self
+  }
+}
+
+impl AsBytes for &str {
+  #[inline]
+  fn as_bytes(&self) -> &[u8] {
+    str::as_bytes(self)
+  }
+}
+
+/// converts an input element to a `char`
+pub trait AsChar: Copy {
+  fn to_char(self) -> char;
+}
+
+impl AsChar for u8 {
+  #[inline]
+  fn to_char(self) -> char {
+    self as char
+  }
+}
+
+impl AsChar for char {
+  #[inline]
+  fn to_char(self) -> char {
+    self
+  }
+}
+
+/// borrows the input as the output of `escaped_transform`
+pub trait ToCow<'a> {
+  /// `str` or `[u8]`
+  type Target: ?Sized + ToOwned + 'a;
+
+  fn to_cow(self) -> Cow<'a, Self::Target>;
+}
+
+impl<'a> ToCow<'a> for &'a [u8] {
+  type Target = [u8];
+
+  #[inline]
+  fn to_cow(self) -> Cow<'a, [u8]> {
+    Cow::Borrowed(self)
+  }
+}
+
+impl<'a> ToCow<'a> for &'a str {
+  type Target = str;
+
+  #[inline]
+  fn to_cow(self) -> Cow<'a, str> {
+    Cow::Borrowed(self)
+  }
+}
+
+/// appends a value to a buffer, used by `escaped_transform` for parts of
+/// the input and the results of the escape parser
+pub trait ExtendInto<C> {
+  fn extend_into(self, acc: &mut C);
+}
+
+impl ExtendInto<Vec<u8>> for &[u8] {
+  #[inline]
+  fn extend_into(self, acc: &mut Vec<u8>) {
+    acc.extend_from_slice(self)
+  }
+}
+
+impl ExtendInto<Vec<u8>> for &str {
+  #[inline]
+  fn extend_into(self, acc: &mut Vec<u8>) {
+    acc.extend_from_slice(self.as_bytes())
+  }
+}
+
+impl ExtendInto<Vec<u8>> for u8 {
+  #[inline]
+  fn extend_into(self, acc: &mut Vec<u8>) {
+    acc.push(self)
+  }
+}
+
+impl ExtendInto<Vec<u8>> for char {
+  #[inline]
+  fn extend_into(self, acc: &mut Vec<u8>) {
+    let mut buf = [0; 4];
+    acc.extend_from_slice(self.encode_utf8(&mut buf).as_bytes())
+  }
+}
+
+impl ExtendInto<String> for &str {
+  #[inline]
+  fn extend_into(self, acc: &mut String) {
+    acc.push_str(self)
+  }
+}
+
+impl ExtendInto<String> for char {
+  #[inline]
+  fn extend_into(self, acc: &mut String) {
+    acc.push(self)
+  }
+}
+
+/// tests an input element, see `take_while`. Implemented for closures and
+/// `ByteSet`
+pub trait Predicate<T> {
+  fn test(&self, c: T) -> bool;
+}
+
+impl<T, F: Fn(T) -> bool> Predicate<T> for F {
+  #[inline]
+  fn test(&self, c: T) -> bool {
+    self(c)
+  }
+}
+
+/// converts a parsed length to a `usize`
+pub trait ToUsize {
+  fn to_usize(&self) -> usize;
+}
+
+macro_rules! to_usize_impl {
+  ( $($ty:ty)* ) => {
+    $(
+      impl ToUsize for $ty {
+        #[inline]
+        fn to_usize(&self) -> usize {
+          *self as usize
+        }
+      }
+    )*
+  };
+}
+
+to_usize_impl!(u8 u16 u32 u64 usize);